@@ -0,0 +1,238 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use multigrid::{Multigrid, Level, CycleOptions, Prolongation, galerkin};
+
+#[derive(Debug, Clone)]
+pub struct AmgOptions {
+    // j is a strong neighbour of i if |a_ij| >= threshold sqrt(|a_ii a_jj|).
+    pub strength_threshold: f64,
+    // weight of the jacobi step smoothing the tentative prolongation, as a
+    // multiple of 1 / rho(D^-1 A). 0 gives plain (unsmoothed) aggregation.
+    pub smoothing_weight: f64,
+    pub max_levels: usize,
+    // stop coarsening once a level has at most this many unknowns.
+    pub max_coarse_size: usize,
+    pub cycle: CycleOptions,
+}
+
+impl Default for AmgOptions {
+    fn default() -> AmgOptions {
+        AmgOptions {
+            strength_threshold: 0.08,
+            smoothing_weight: 4.0 / 3.0,
+            max_levels: 10,
+            max_coarse_size: 64,
+            cycle: CycleOptions::default(),
+        }
+    }
+}
+
+// smoothed aggregation algebraic multigrid, see:
+// Vanek, Mandel and Brezina, "Algebraic multigrid by smoothed aggregation
+// for second and fourth order elliptic problems", 1996.
+pub fn amg(matrix: &SparseSymmetricMatrix, options: &AmgOptions) -> Multigrid {
+    let mut levels = vec![];
+    let mut current = SparseSymmetricMatrix {
+        length: matrix.length,
+        indices: matrix.indices.clone(),
+        values: matrix.values.clone(),
+    };
+    while levels.len() + 1 < options.max_levels && current.length + 1 > options.max_coarse_size {
+        let (aggregates, count) = aggregate(&current, options.strength_threshold);
+        if count == 0 || count == current.length + 1 {
+            break;
+        }
+        let level = Level::new(current, vec![]);
+        let prolongation = smoothed_prolongation(&level, &aggregates, count, options);
+        let coarse = galerkin(&level.matrix, &prolongation, count);
+        levels.push(Level { prolongation, ..level });
+        current = coarse;
+    }
+    levels.push(Level::new(current, vec![]));
+    Multigrid::new(levels, options.cycle.clone())
+}
+
+fn strong_neighbours(matrix: &SparseSymmetricMatrix, threshold: f64) -> Vec<Vec<usize>> {
+    let n = matrix.length + 1;
+    let mut diagonals = vec![0.0; n];
+    for (i, diagonal) in diagonals.iter_mut().enumerate() {
+        for (j, &index) in matrix.indices[i].iter().enumerate() {
+            if index == i {
+                *diagonal = matrix.values[i][j];
+            }
+        }
+    }
+    (0..n)
+        .map(|i| {
+            matrix.indices[i]
+                .iter()
+                .zip(matrix.values[i].iter())
+                .filter(|&(&j, &value)| {
+                    j != i && value != 0.0 &&
+                        value.abs() >= threshold * (diagonals[i] * diagonals[j]).abs().sqrt()
+                })
+                .map(|(&j, _)| j)
+                .collect()
+        })
+        .collect()
+}
+
+// greedy aggregation in three passes. unknowns without strong neighbours are
+// left out (None), the smoother takes care of them.
+fn aggregate(matrix: &SparseSymmetricMatrix, threshold: f64) -> (Vec<Option<usize>>, usize) {
+    let neighbours = strong_neighbours(matrix, threshold);
+    let n = neighbours.len();
+    let mut aggregates: Vec<Option<usize>> = vec![None; n];
+    let mut count = 0;
+
+    // whole neighbourhoods that are still free become aggregates.
+    for i in 0..n {
+        if neighbours[i].is_empty() || aggregates[i].is_some() {
+            continue;
+        }
+        if neighbours[i].iter().all(|&j| aggregates[j].is_none()) {
+            aggregates[i] = Some(count);
+            for &j in &neighbours[i] {
+                aggregates[j] = Some(count);
+            }
+            count += 1;
+        }
+    }
+
+    // join a neighbouring aggregate from the first pass.
+    let first_pass = aggregates.clone();
+    for i in 0..n {
+        if aggregates[i].is_none() {
+            aggregates[i] = neighbours[i].iter().filter_map(|&j| first_pass[j]).next();
+        }
+    }
+
+    // whatever remains is aggregated with its free neighbours.
+    for i in 0..n {
+        if neighbours[i].is_empty() || aggregates[i].is_some() {
+            continue;
+        }
+        aggregates[i] = Some(count);
+        for &j in &neighbours[i] {
+            if aggregates[j].is_none() {
+                aggregates[j] = Some(count);
+            }
+        }
+        count += 1;
+    }
+    (aggregates, count)
+}
+
+// P = (I - w D^-1 A) T, where T is the tentative prolongation, piecewise
+// constant on each aggregate with unit norm columns.
+fn smoothed_prolongation(
+    level: &Level,
+    aggregates: &[Option<usize>],
+    count: usize,
+    options: &AmgOptions,
+) -> Prolongation {
+    let matrix = &level.matrix;
+    let mut sizes = vec![0.0; count];
+    for aggregate in aggregates.iter().filter_map(|&a| a) {
+        sizes[aggregate] += 1.0;
+    }
+    let tentative = |i: usize| aggregates[i].map(|a| (a, 1.0 / (sizes[a] as f64).sqrt()));
+
+    // gershgorin bound on the spectral radius of D^-1 A.
+    let mut radius: f64 = 0.0;
+    for i in 0..level.len() {
+        if level.diagonals[i] != 0.0 {
+            let sum = matrix.values[i].iter().fold(0.0, |acc, v| acc + v.abs());
+            radius = radius.max(sum / level.diagonals[i].abs());
+        }
+    }
+    let weight = if radius > 0.0 {
+        options.smoothing_weight / radius
+    } else {
+        0.0
+    };
+
+    (0..level.len())
+        .map(|i| {
+            let mut row: Vec<(usize, f64)> = tentative(i).into_iter().collect();
+            if level.diagonals[i] != 0.0 && weight != 0.0 {
+                let scale = weight / level.diagonals[i];
+                for (j, &index) in matrix.indices[i].iter().enumerate() {
+                    if let Some((a, t)) = tentative(index) {
+                        let value = -scale * matrix.values[i][j] * t;
+                        match row.iter().position(|&(c, _)| c == a) {
+                            Some(p) => row[p].1 += value,
+                            None => row.push((a, value)),
+                        }
+                    }
+                }
+            }
+            row.sort_by_key(|&(c, _)| c);
+            row
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use approximate_inverse::ApproximateInverse;
+    use preconditioner::Preconditioner;
+    use multigrid::{CycleOptions, Smoother, CoarseSolve};
+    use test_matrices::{laplacian_2d, converged_iterations};
+    use amg::{amg, AmgOptions};
+
+    #[test]
+    fn test_hierarchy() {
+        let m = laplacian_2d(32, 32);
+        let options = AmgOptions::default();
+        let multigrid = amg(&m, &options);
+        assert!(multigrid.levels.len() > 2);
+        for pair in multigrid.levels.windows(2) {
+            assert!(pair[1].len() < pair[0].len());
+            assert_eq!(pair[0].prolongation.len(), pair[0].len());
+        }
+        let coarsest = &multigrid.levels[multigrid.levels.len() - 1];
+        assert!(coarsest.len() <= options.max_coarse_size);
+        assert!(coarsest.prolongation.is_empty());
+    }
+
+    #[test]
+    fn test_symmetric() {
+        let m = laplacian_2d(12, 12);
+        let u = Vector((0..144).map(|i| ((i * 7) % 13) as f64 - 6.0).collect());
+        let v = Vector((0..144).map(|i| ((i * 5) % 11) as f64 - 5.0).collect());
+        for &(smoother, coarse_solve) in &[
+            (Smoother::GaussSeidel, CoarseSolve::Direct),
+            (Smoother::Jacobi(0.6), CoarseSolve::Direct),
+            (Smoother::GaussSeidel, CoarseSolve::Smooth(2)),
+        ]
+        {
+            let options = AmgOptions {
+                max_coarse_size: 8,
+                cycle: CycleOptions {
+                    smoother,
+                    sweeps: 2,
+                    coarse_solve,
+                },
+                ..AmgOptions::default()
+            };
+            let multigrid = amg(&m, &options);
+            let uv = u.dot(&multigrid.apply(&v));
+            let vu = v.dot(&multigrid.apply(&u));
+            assert!((uv - vu).abs() < 1e-10 * uv.abs());
+        }
+    }
+
+    #[test]
+    fn test_fewer_iterations_than_mic() {
+        let m = laplacian_2d(48, 48);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 48 * 48]);
+        let mic = converged_iterations(&srm, &Preconditioner::new(&m), &v);
+        let multigrid = converged_iterations(&srm, &amg(&m, &AmgOptions::default()), &v);
+        assert!(multigrid < mic);
+    }
+}
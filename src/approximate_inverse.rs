@@ -0,0 +1,17 @@
+use vector::Vector;
+
+// trait for approximate inverses M^-1 of a matrix, used by the solvers to
+// accelerate convergence. conjugate gradient and MINRES need it to be
+// symmetric positive definite.
+pub trait ApproximateInverse {
+    fn apply(&self, v: &Vector) -> Vector;
+}
+
+// no preconditioning at all, i.e. plain conjugate gradient.
+pub struct Identity;
+
+impl ApproximateInverse for Identity {
+    fn apply(&self, v: &Vector) -> Vector {
+        v.clone()
+    }
+}
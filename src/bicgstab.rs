@@ -0,0 +1,226 @@
+use vector::Vector;
+use linear_operator::LinearOperator;
+use approximate_inverse::ApproximateInverse;
+use solver::{SolverResult, SolverOptions, StopReason, ResidualNorm};
+
+// BiCGSTAB for general, nonsymmetric, systems. it needs a fixed number of
+// vectors, unlike GMRES, at the price of an irregular convergence and
+// possible breakdowns, which are reported in the result. the preconditioner
+// is applied from the right, so the residual is the true one, and the
+// preconditioned norm is not available; the euclidean norm is used in its
+// place.
+pub fn solve_bicgstab<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    let n = a.dimension();
+    options.assert_no_null_space();
+    assert_eq!(n, rhs.0.len());
+    assert_eq!(n, x0.0.len());
+    if rhs.largest_absolute_value() == 0.0 {
+        return SolverResult::zero_rhs(rhs, options);
+    }
+    let measure = |r: &Vector| match options.residual_norm {
+        ResidualNorm::MaxAbsolute => options.measure(r, 0.0),
+        _ => r.norm(),
+    };
+    let tolerance = options.tolerance(measure(rhs));
+
+    let mut result = x0.clone();
+    let mut v = Vector(vec![0.0; n]);
+    a.apply_into(&result, &mut v);
+    let mut r = rhs.clone() + v.scale(-1.0);
+    let initial_residual = measure(&r);
+    let mut history = if options.record_history {
+        Some(vec![initial_residual])
+    } else {
+        None
+    };
+    if initial_residual <= tolerance {
+        return SolverResult::new(
+            StopReason::Converged,
+            0,
+            result,
+            r,
+            initial_residual,
+            initial_residual,
+            history,
+        );
+    }
+
+    // the shadow residual, fixed for the whole iteration.
+    let shadow = r.clone();
+    let mut p = r.clone();
+    let mut t = Vector(vec![0.0; n]);
+    let mut rho = shadow.dot(&r);
+    let mut residual = initial_residual;
+
+    for iteration in 0..options.max_iterations {
+        let p_hat = preconditioner.apply(&p);
+        a.apply_into(&p_hat, &mut v);
+        let denominator = shadow.dot(&v);
+        if !denominator.is_normal() {
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        let alpha = rho / denominator;
+        let s = r + v.scale(-alpha);
+        let s_residual = measure(&s);
+        if s_residual <= tolerance {
+            // converged after the first half step.
+            result = result + p_hat.scale(alpha);
+            if let Some(ref mut history) = history {
+                history.push(s_residual);
+            }
+            return SolverResult::new(
+                StopReason::Converged,
+                iteration + 1,
+                result,
+                s,
+                initial_residual,
+                s_residual,
+                history,
+            );
+        }
+
+        let s_hat = preconditioner.apply(&s);
+        a.apply_into(&s_hat, &mut t);
+        let t_squared = t.dot(&t);
+        let omega = if t_squared.is_normal() {
+            t.dot(&s) / t_squared
+        } else {
+            0.0
+        };
+        result = result + p_hat.scale(alpha) + s_hat.scale(omega);
+        r = s + t.scale(-omega);
+        residual = measure(&r);
+        if let Some(ref mut history) = history {
+            history.push(residual);
+        }
+        if residual <= tolerance {
+            return SolverResult::new(
+                StopReason::Converged,
+                iteration + 1,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+
+        let rho_new = shadow.dot(&r);
+        if !omega.is_normal() || !rho_new.is_normal() {
+            // the stabilizing step stagnated, or the residual became
+            // orthogonal to the shadow residual.
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration + 1,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        let beta = (rho_new / rho) * (alpha / omega);
+        p = r.clone() + (p + v.scale(-omega)).scale(beta);
+        rho = rho_new;
+    }
+
+    SolverResult::new(
+        StopReason::MaxIterations,
+        options.max_iterations,
+        result,
+        r,
+        initial_residual,
+        residual,
+        history,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::Entry;
+    use sparse_row_matrix::SparseRowMatrix;
+    use approximate_inverse::Identity;
+    use jacobi::Jacobi;
+    use vector::Vector;
+    use solver::{SolverOptions, StopReason, ResidualNorm};
+    use test_matrices::{convection_diffusion, assert_true_residual};
+    use bicgstab::solve_bicgstab;
+
+    #[test]
+    fn test_convection_diffusion() {
+        let a = convection_diffusion(16, 2.0, &[1.0; 256]);
+        let v = Vector((0..256).map(|i| ((i * 3) % 5) as f64).collect());
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .residual_norm(ResidualNorm::L2)
+            .record_history(true);
+        let result = solve_bicgstab(&a, &Identity, &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert_true_residual(&a, &v, &result.best_guess, &result.residual);
+        assert!(result.final_residual_norm <= 1e-8 * v.norm());
+        assert_eq!(result.residual_history.unwrap().len(), result.iterations + 1);
+    }
+
+    #[test]
+    fn test_preconditioned() {
+        let scale = (0..256).map(|i| 1.0 + (i % 17) as f64).collect::<Vec<f64>>();
+        let a = convection_diffusion(16, 10.0, &scale);
+        let v = Vector(vec![1.0; 256]);
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(1000);
+        let plain = solve_bicgstab(&a, &Identity, &v, &x0, &options);
+        let jacobi = Jacobi {
+            inverse_diagonals: scale.iter().map(|s| 1.0 / (14.0 * s)).collect(),
+        };
+        let preconditioned = solve_bicgstab(&a, &jacobi, &v, &x0, &options);
+        assert_eq!(preconditioned.stop_reason, StopReason::Converged);
+        assert!(preconditioned.iterations < plain.iterations);
+        assert_true_residual(&a, &v, &preconditioned.best_guess, &preconditioned.residual);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        // a rotation: A r is orthogonal to r, so the first step breaks down.
+        let a = SparseRowMatrix::from_entries(&vec![
+            Entry { x: 0, y: 1, v: 1.0 },
+            Entry { x: 1, y: 0, v: -1.0 },
+        ]);
+        let v = Vector(vec![1.0, 0.0]);
+        let x0 = Vector(vec![0.0; 2]);
+        let result = solve_bicgstab(&a, &Identity, &v, &x0, &SolverOptions::new());
+        assert_eq!(result.stop_reason, StopReason::Breakdown);
+        assert_eq!(result.iterations, 0);
+        assert_eq!(result.best_guess.0, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_max_iterations() {
+        let a = convection_diffusion(16, 2.0, &[1.0; 256]);
+        let v = Vector(vec![1.0; 256]);
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-12)
+            .max_iterations(3);
+        let result = solve_bicgstab(&a, &Identity, &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::MaxIterations);
+        assert_eq!(result.iterations, 3);
+        assert_true_residual(&a, &v, &result.best_guess, &result.residual);
+    }
+}
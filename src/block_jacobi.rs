@@ -0,0 +1,178 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use preconditioner::Preconditioner;
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+
+// block jacobi preconditioner: every block of unknowns gets its own
+// incomplete cholesky factor of the diagonal block, and the blocks are
+// applied independently. with overlap this is additive schwarz, where the
+// contributions of overlapping blocks are summed. unknowns that are in no
+// block are left as null rows.
+pub struct BlockJacobi {
+    pub blocks: Vec<Vec<usize>>,
+    pub factors: Vec<Preconditioner>,
+}
+
+impl BlockJacobi {
+    // each block is grown by `overlap` layers of neighbours in the matrix
+    // graph before it is factored.
+    pub fn new(
+        matrix: &SparseSymmetricMatrix,
+        blocks: &[Vec<usize>],
+        overlap: usize,
+    ) -> BlockJacobi {
+        let blocks = blocks
+            .iter()
+            .map(|block| grow(matrix, block, overlap))
+            .collect::<Vec<Vec<usize>>>();
+        let factors = blocks
+            .iter()
+            .map(|block| Preconditioner::new(&submatrix(matrix, block)))
+            .collect();
+        BlockJacobi { blocks, factors }
+    }
+
+    pub fn contiguous(
+        matrix: &SparseSymmetricMatrix,
+        block_size: usize,
+        overlap: usize,
+    ) -> BlockJacobi {
+        let blocks = contiguous_blocks(matrix.length + 1, block_size);
+        BlockJacobi::new(matrix, &blocks, overlap)
+    }
+}
+
+impl ApproximateInverse for BlockJacobi {
+    fn apply(&self, v: &Vector) -> Vector {
+        let mut result = Vector(vec![0.0; v.0.len()]);
+        for (block, factor) in self.blocks.iter().zip(self.factors.iter()) {
+            let local = Vector(block.iter().map(|&i| v.0[i]).collect());
+            let local = factor.apply(&local);
+            for (&i, value) in block.iter().zip(local.0.iter()) {
+                result.0[i] += *value;
+            }
+        }
+        result
+    }
+}
+
+// partition 0..n into consecutive ranges of block_size unknowns.
+pub fn contiguous_blocks(n: usize, block_size: usize) -> Vec<Vec<usize>> {
+    assert!(block_size > 0);
+    (0..n)
+        .step_by(block_size)
+        .map(|start| (start..n.min(start + block_size)).collect())
+        .collect()
+}
+
+// the block plus its neighbours up to the given graph distance, sorted.
+fn grow(matrix: &SparseSymmetricMatrix, block: &[usize], overlap: usize) -> Vec<usize> {
+    assert!(!block.is_empty());
+    let mut inside = vec![false; matrix.length + 1];
+    let mut frontier = block.to_vec();
+    for &i in block {
+        inside[i] = true;
+    }
+    for _ in 0..overlap {
+        let mut next = vec![];
+        for &i in &frontier {
+            for &j in &matrix.indices[i] {
+                if !inside[j] {
+                    inside[j] = true;
+                    next.push(j);
+                }
+            }
+        }
+        frontier = next;
+    }
+    (0..matrix.length + 1).filter(|&i| inside[i]).collect()
+}
+
+// the diagonal block of the matrix on the given sorted unknowns.
+fn submatrix(matrix: &SparseSymmetricMatrix, block: &[usize]) -> SparseSymmetricMatrix {
+    let mut local = vec![None; matrix.length + 1];
+    for (k, &i) in block.iter().enumerate() {
+        local[i] = Some(k);
+    }
+    let mut indices = vec![];
+    let mut values = vec![];
+    for &i in block {
+        let mut row_indices = vec![];
+        let mut row_values = vec![];
+        for (&j, &value) in matrix.indices[i].iter().zip(matrix.values[i].iter()) {
+            if let Some(k) = local[j] {
+                row_indices.push(k);
+                row_values.push(value);
+            }
+        }
+        indices.push(row_indices);
+        values.push(row_values);
+    }
+    SparseSymmetricMatrix {
+        length: block.len() - 1,
+        indices,
+        values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use preconditioner::Preconditioner;
+    use jacobi::Jacobi;
+    use approximate_inverse::{ApproximateInverse, Identity};
+    use test_matrices::{laplacian_2d, converged_iterations};
+    use block_jacobi::{BlockJacobi, contiguous_blocks};
+
+    #[test]
+    fn test_contiguous_blocks() {
+        assert_eq!(
+            contiguous_blocks(7, 3),
+            vec![vec![0, 1, 2], vec![3, 4, 5], vec![6]]
+        );
+        assert_eq!(contiguous_blocks(4, 2), vec![vec![0, 1], vec![2, 3]]);
+    }
+
+    #[test]
+    fn test_single_block() {
+        let m = laplacian_2d(5, 5);
+        let blocks = BlockJacobi::contiguous(&m, 25, 0);
+        let full = Preconditioner::new(&m);
+        let v = Vector((0..25).map(|i| i as f64).collect());
+        assert_eq!(blocks.apply(&v).0, full.apply(&v).0);
+    }
+
+    #[test]
+    fn test_unit_blocks() {
+        // blocks of one unknown are plain jacobi.
+        let m = laplacian_2d(5, 5);
+        let blocks = BlockJacobi::contiguous(&m, 1, 0);
+        let jacobi = Jacobi::new(&m);
+        let v = Vector((0..25).map(|i| i as f64).collect());
+        assert_eq!(blocks.apply(&v).0, jacobi.apply(&v).0);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let m = laplacian_2d(4, 4);
+        let blocks = BlockJacobi::new(&m, &[vec![0]], 1);
+        assert_eq!(blocks.blocks, vec![vec![0, 1, 4]]);
+        let blocks = BlockJacobi::new(&m, &[vec![0]], 2);
+        assert_eq!(blocks.blocks, vec![vec![0, 1, 2, 4, 5, 8]]);
+    }
+
+    #[test]
+    fn test_iterations() {
+        let m = laplacian_2d(24, 24);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 576]);
+        let plain = converged_iterations(&srm, &Identity, &v);
+        let jacobi = converged_iterations(&srm, &BlockJacobi::contiguous(&m, 96, 0), &v);
+        let schwarz = converged_iterations(&srm, &BlockJacobi::contiguous(&m, 96, 2), &v);
+        assert!(jacobi < plain);
+        assert!(schwarz < jacobi);
+    }
+}
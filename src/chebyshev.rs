@@ -0,0 +1,215 @@
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use sparse_row_matrix::SparseRowMatrix;
+use linear_operator::LinearOperator;
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+
+const LANCZOS_STEPS: usize = 20;
+// the estimated smallest eigenvalue is kept at least this fraction of the
+// largest, for singular or badly conditioned matrices.
+const MIN_BOUND_RATIO: f64 = 1e-4;
+
+// polynomial preconditioner: degree steps of the chebyshev iteration for
+// A x = v starting from zero, tuned to the spectrum [lambda_min, lambda_max].
+// it only needs products with the matrix, so it has no sequential sweeps.
+// lambda_max must not be smaller than the largest eigenvalue, or the
+// preconditioner is no longer positive definite.
+pub struct Chebyshev {
+    pub matrix: SparseRowMatrix,
+    pub degree: usize,
+    pub lambda_min: f64,
+    pub lambda_max: f64,
+}
+
+impl Chebyshev {
+    // with eigenvalue bounds estimated by a few lanczos steps.
+    pub fn new(matrix: &SparseSymmetricMatrix, degree: usize) -> Chebyshev {
+        let srm = SparseRowMatrix::new(matrix);
+        let (lambda_min, lambda_max) = lanczos_bounds(&srm, LANCZOS_STEPS);
+        let gershgorin = matrix.values.iter().fold(0.0, |acc: f64, row| {
+            acc.max(row.iter().fold(0.0, |sum, v| sum + v.abs()))
+        });
+        let lambda_max = lambda_max.min(gershgorin);
+        Chebyshev::from_parts(srm, degree, lambda_min.min(0.5 * lambda_max), lambda_max)
+    }
+
+    pub fn with_bounds(
+        matrix: &SparseSymmetricMatrix,
+        degree: usize,
+        lambda_min: f64,
+        lambda_max: f64,
+    ) -> Chebyshev {
+        let srm = SparseRowMatrix::new(matrix);
+        Chebyshev::from_parts(srm, degree, lambda_min, lambda_max)
+    }
+
+    fn from_parts(
+        matrix: SparseRowMatrix,
+        degree: usize,
+        lambda_min: f64,
+        lambda_max: f64,
+    ) -> Chebyshev {
+        assert!(degree > 0);
+        assert!(lambda_min > 0.0 && lambda_max > lambda_min);
+        Chebyshev {
+            matrix,
+            degree,
+            lambda_min,
+            lambda_max,
+        }
+    }
+}
+
+impl ApproximateInverse for Chebyshev {
+    fn apply(&self, v: &Vector) -> Vector {
+        let theta = (self.lambda_max + self.lambda_min) / 2.0;
+        let delta = (self.lambda_max - self.lambda_min) / 2.0;
+        let sigma = theta / delta;
+        let mut rho = 1.0 / sigma;
+        let mut r = v.clone();
+        let mut d = v.scale(1.0 / theta);
+        let mut result = Vector(vec![0.0; v.0.len()]);
+        for k in 0..self.degree {
+            result = result + d.clone();
+            if k + 1 == self.degree {
+                break;
+            }
+            r = r + self.matrix.apply(&d).scale(-1.0);
+            let rho_new = 1.0 / (2.0 * sigma - rho);
+            d = d.scale(rho_new * rho) + r.scale(2.0 * rho_new / delta);
+            rho = rho_new;
+        }
+        result
+    }
+}
+
+// estimates (lambda_min, lambda_max) of a symmetric positive definite
+// operator from the ritz values of a few lanczos steps. the upper bound is
+// padded with the last off diagonal, which in practice makes it an upper
+// bound of the spectrum.
+pub fn lanczos_bounds<A: LinearOperator>(a: &A, steps: usize) -> (f64, f64) {
+    let n = a.dimension();
+    let steps = steps.min(n);
+    assert!(steps > 0);
+    // a fixed start vector that is not aligned with simple grid modes.
+    let start = Vector((0..n).map(|i| 0.5 + ((i * 7919) % 101) as f64 / 101.0).collect());
+    let mut v = start.scale(1.0 / start.norm());
+    let mut previous = Vector(vec![0.0; n]);
+    let mut w = Vector(vec![0.0; n]);
+    let mut alphas = vec![];
+    let mut betas = vec![];
+    let mut beta = 0.0;
+    for _ in 0..steps {
+        a.apply_into(&v, &mut w);
+        let mut next = w.clone() + previous.scale(-beta);
+        let alpha = next.dot(&v);
+        next = next + v.scale(-alpha);
+        alphas.push(alpha);
+        beta = next.norm();
+        betas.push(beta);
+        if beta <= 1e-12 * alpha.abs() {
+            // invariant subspace, the ritz values are exact.
+            break;
+        }
+        previous = v;
+        v = next.scale(1.0 / beta);
+    }
+
+    let residual = betas.pop().unwrap_or(0.0);
+    let (smallest, largest) = tridiagonal_extremes(&alphas, &betas);
+    let lambda_max = largest + residual;
+    (smallest.max(MIN_BOUND_RATIO * lambda_max), lambda_max)
+}
+
+// extreme eigenvalues of a symmetric tridiagonal matrix by sturm sequence
+// bisection.
+fn tridiagonal_extremes(alphas: &[f64], betas: &[f64]) -> (f64, f64) {
+    let n = alphas.len();
+    let mut low = f64::INFINITY;
+    let mut high = f64::NEG_INFINITY;
+    for i in 0..n {
+        let radius = if i > 0 { betas[i - 1].abs() } else { 0.0 } +
+            if i + 1 < n { betas[i].abs() } else { 0.0 };
+        low = low.min(alphas[i] - radius);
+        high = high.max(alphas[i] + radius);
+    }
+
+    // number of eigenvalues below x.
+    let count = |x: f64| {
+        let mut result = 0;
+        let mut q = 1.0;
+        for i in 0..n {
+            let coupling = if i > 0 { betas[i - 1] * betas[i - 1] } else { 0.0 };
+            q = alphas[i] - x - coupling / q;
+            if q == 0.0 {
+                q = f64::EPSILON * (alphas[i].abs() + 1.0);
+            }
+            if q < 0.0 {
+                result += 1;
+            }
+        }
+        result
+    };
+
+    let bisect = |target: usize| {
+        let (mut a, mut b) = (low, high);
+        for _ in 0..100 {
+            let middle = (a + b) / 2.0;
+            if count(middle) >= target {
+                b = middle;
+            } else {
+                a = middle;
+            }
+        }
+        (a + b) / 2.0
+    };
+    (bisect(1), bisect(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use approximate_inverse::{ApproximateInverse, Identity};
+    use test_matrices::{laplacian_1d, laplacian_2d, converged_iterations};
+    use chebyshev::{Chebyshev, lanczos_bounds};
+
+    #[test]
+    fn test_lanczos_bounds() {
+        let n = 40;
+        let m = laplacian_1d(n);
+        let angle = ::std::f64::consts::PI / (n + 1) as f64;
+        let smallest = 2.0 - 2.0 * angle.cos();
+        let largest = 2.0 - 2.0 * (n as f64 * angle).cos();
+        let (lambda_min, lambda_max) = lanczos_bounds(&SparseRowMatrix::new(&m), 20);
+        assert!(lambda_min >= smallest - 1e-12);
+        assert!(lambda_max >= largest);
+        assert!(lambda_max < 1.5 * largest);
+        let chebyshev = Chebyshev::new(&m, 4);
+        assert!(chebyshev.lambda_max >= largest);
+        assert!(chebyshev.lambda_max <= 4.0);
+    }
+
+    #[test]
+    fn test_positive_definite() {
+        let m = laplacian_2d(10, 10);
+        let chebyshev = Chebyshev::new(&m, 6);
+        let u = Vector((0..100).map(|i| ((i * 7) % 13) as f64 - 6.0).collect());
+        let v = Vector((0..100).map(|i| ((i * 5) % 11) as f64 - 5.0).collect());
+        let uv = u.dot(&chebyshev.apply(&v));
+        let vu = v.dot(&chebyshev.apply(&u));
+        assert!((uv - vu).abs() < 1e-10 * uv.abs());
+        assert!(u.dot(&chebyshev.apply(&u)) > 0.0);
+        assert!(v.dot(&chebyshev.apply(&v)) > 0.0);
+    }
+
+    #[test]
+    fn test_fewer_iterations_than_plain_cg() {
+        let m = laplacian_2d(24, 24);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 576]);
+        let plain = converged_iterations(&srm, &Identity, &v);
+        let chebyshev = converged_iterations(&srm, &Chebyshev::new(&m, 8), &v);
+        assert!(2 * chebyshev < plain);
+    }
+}
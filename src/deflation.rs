@@ -0,0 +1,227 @@
+use std::vec::Vec;
+
+use vector::Vector;
+use dense::DenseCholesky;
+use linear_operator::LinearOperator;
+use approximate_inverse::ApproximateInverse;
+use solver::{SolverResult, SolverOptions, conjugate_gradient};
+
+// the deflation space W, spanned by a few vectors that approximate the
+// eigenvectors of the smallest eigenvalues, together with AW and the
+// factored coarse matrix E = W^T A W.
+pub struct Deflation {
+    pub vectors: Vec<Vector>,
+    pub products: Vec<Vector>,
+    pub coarse: DenseCholesky,
+}
+
+impl Deflation {
+    // linearly dependent vectors leave null rows in the coarse factor and
+    // are effectively ignored.
+    pub fn new<A: LinearOperator>(a: &A, vectors: Vec<Vector>) -> Deflation {
+        let n = a.dimension();
+        let products = vectors
+            .iter()
+            .map(|w| {
+                assert_eq!(n, w.0.len());
+                let mut product = Vector(vec![0.0; n]);
+                a.apply_into(w, &mut product);
+                product
+            })
+            .collect::<Vec<Vector>>();
+        let coarse = vectors
+            .iter()
+            .map(|w| products.iter().map(|aw| w.dot(aw)).collect())
+            .collect::<Vec<Vec<f64>>>();
+        let coarse = DenseCholesky::new(&coarse);
+        Deflation {
+            vectors,
+            products,
+            coarse,
+        }
+    }
+
+    // W E^-1 V^T v, for V either W or AW.
+    fn project(&self, basis: &[Vector], v: &Vector) -> Vector {
+        let coefficients = basis.iter().map(|b| b.dot(v)).collect::<Vec<f64>>();
+        let coefficients = self.coarse.solve(&coefficients);
+        self.vectors.iter().zip(coefficients.iter()).fold(
+            Vector(vec![0.0; v.0.len()]),
+            |acc, (w, &c)| acc + w.scale(c),
+        )
+    }
+}
+
+// indicator vectors of the given subdomains, the usual choice of
+// deflation vectors for problems with a few weakly coupled regions.
+pub fn indicator_vectors(n: usize, subdomains: &[Vec<usize>]) -> Vec<Vector> {
+    subdomains
+        .iter()
+        .map(|subdomain| {
+            let mut w = vec![0.0; n];
+            for &i in subdomain {
+                w[i] = 1.0;
+            }
+            Vector(w)
+        })
+        .collect()
+}
+
+// deflated preconditioned conjugate gradient. the initial guess is corrected
+// so that the residual is orthogonal to W, and every search direction is
+// kept A-orthogonal to W, which removes the deflated eigenvalues from the
+// spectrum the iteration sees.
+pub fn solve_deflated<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    deflation: &Deflation,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    // the correction has to see the right hand side the iteration sees.
+    let rhs = &options.project(rhs.clone());
+    let mut q = Vector(vec![0.0; a.dimension()]);
+    a.apply_into(x0, &mut q);
+    let r = rhs.clone() + q.scale(-1.0);
+    let x0 = x0.clone() + deflation.project(&deflation.vectors, &r);
+    let correction = |z: &Vector| deflation.project(&deflation.products, z);
+    conjugate_gradient(a, preconditioner, rhs, &x0, options, Some(&correction))
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use preconditioner::Preconditioner;
+    use vector::Vector;
+    use solver::{solve_operator, SolverOptions, StopReason};
+    use test_matrices::{neumann, assert_true_residual};
+    use deflation::{Deflation, indicator_vectors, solve_deflated};
+
+    // diffusion on an n x n grid, grounded on its left edge and cut into
+    // vertical slabs that are only weakly coupled to each other.
+    fn weakly_coupled(n: usize, slab: usize) -> SparseSymmetricMatrix {
+        let mut entries = vec![];
+        let mut diagonals = vec![0.0; n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * n + x;
+                if x + 1 < n {
+                    let weight = if (x + 1) % slab == 0 { 1e-5 } else { 1.0 };
+                    entries.push(Entry { x: i, y: i + 1, v: -weight });
+                    diagonals[i] += weight;
+                    diagonals[i + 1] += weight;
+                }
+                if y + 1 < n {
+                    entries.push(Entry { x: i, y: i + n, v: -1.0 });
+                    diagonals[i] += 1.0;
+                    diagonals[i + n] += 1.0;
+                }
+                if x == 0 {
+                    diagonals[i] += 1.0;
+                }
+            }
+        }
+        for (i, &d) in diagonals.iter().enumerate() {
+            entries.push(Entry { x: i, y: i, v: d });
+        }
+        SparseSymmetricMatrix::new(&entries)
+    }
+
+    fn slabs(n: usize, slab: usize) -> Vec<Vec<usize>> {
+        (0..n / slab)
+            .map(|s| {
+                (0..n * n)
+                    .filter(|i| (i % n) / slab == s)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_coarse_matrix() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 2.0 },
+            Entry { x: 0, y: 1, v: -1.0 },
+            Entry { x: 1, y: 1, v: 2.0 },
+            Entry { x: 2, y: 2, v: 3.0 },
+        ]);
+        let srm = SparseRowMatrix::new(&m);
+        let vectors = indicator_vectors(3, &[vec![0, 1], vec![2]]);
+        let deflation = Deflation::new(&srm, vectors);
+        let solution = deflation.coarse.solve(&[2.0, 3.0]);
+        assert!((solution[0] - 1.0).abs() < 1e-12);
+        assert!((solution[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_deflated_iterations() {
+        let n = 32;
+        let m = weakly_coupled(n, 8);
+        let srm = SparseRowMatrix::new(&m);
+        let ic_factor = Preconditioner::new(&m);
+        let v = Vector((0..n * n).map(|i| ((i * 7) % 5) as f64 - 2.0).collect());
+        let x0 = Vector(vec![0.0; n * n]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(500);
+        let plain = solve_operator(&srm, &ic_factor, &v, &x0, &options);
+        let deflation = Deflation::new(&srm, indicator_vectors(n * n, &slabs(n, 8)));
+        let deflated = solve_deflated(&srm, &ic_factor, &deflation, &v, &x0, &options);
+        assert_eq!(plain.stop_reason, StopReason::Converged);
+        assert_eq!(deflated.stop_reason, StopReason::Converged);
+        assert!(2 * deflated.iterations < plain.iterations);
+
+        // the returned residual is the true one and stays orthogonal to W.
+        assert_true_residual(&srm, &v, &deflated.best_guess, &deflated.residual);
+        for w in &deflation.vectors {
+            assert!(w.dot(&deflated.residual).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_zero_rhs() {
+        let m = weakly_coupled(4, 2);
+        let srm = SparseRowMatrix::new(&m);
+        let deflation = Deflation::new(&srm, indicator_vectors(16, &slabs(4, 2)));
+        let zero = Vector(vec![0.0; 16]);
+        let result = solve_deflated(
+            &srm,
+            &Preconditioner::new(&m),
+            &deflation,
+            &zero,
+            &zero,
+            &SolverOptions::new(),
+        );
+        assert_eq!(result.stop_reason, StopReason::ZeroRhs);
+        assert_eq!(result.best_guess.0, vec![0.0; 16]);
+    }
+
+    #[test]
+    fn test_null_space() {
+        let n = 16;
+        let m = neumann(n);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector((0..n * n).map(|i| (i % 7) as f64).collect());
+        let x0 = Vector(vec![0.0; n * n]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(500)
+            .null_space(vec![Vector(vec![1.0; n * n])]);
+        // the constant must not be in the deflation space, so only the
+        // left half is deflated.
+        let subdomains = slabs(n, 4);
+        let deflation = Deflation::new(&srm, indicator_vectors(n * n, &subdomains[..2]));
+        let ic_factor = Preconditioner::new(&m);
+        let result = solve_deflated(&srm, &ic_factor, &deflation, &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        let mean = result.best_guess.0.iter().sum::<f64>() / (n * n) as f64;
+        assert!(mean.abs() < 1e-12);
+        let projected = options.project(v.clone());
+        let product = srm.apply(&result.best_guess);
+        for (a, b) in projected.0.iter().zip(product.0.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+}
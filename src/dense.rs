@@ -0,0 +1,96 @@
+use std::vec::Vec;
+
+// cholesky factorization of a small dense symmetric positive (semi)definite
+// matrix, for coarse problems that are cheaper to solve directly.
+pub struct DenseCholesky {
+    pub length: usize,
+    // lower triangle, stored by row.
+    pub lower: Vec<Vec<f64>>,
+}
+
+// pivots below this fraction of the original diagonal are treated as zero,
+// so a semidefinite matrix leaves null rows instead of blowing up.
+const PIVOT_TOLERANCE: f64 = 1e-12;
+
+impl DenseCholesky {
+    pub fn new(matrix: &[Vec<f64>]) -> DenseCholesky {
+        let length = matrix.len();
+        let mut lower = vec![vec![0.0; length]; length];
+        for i in 0..length {
+            assert_eq!(matrix[i].len(), length);
+            for j in 0..i + 1 {
+                let sum = lower[i][..j]
+                    .iter()
+                    .zip(lower[j][..j].iter())
+                    .fold(matrix[i][j], |acc, (a, b)| acc - a * b);
+                if i == j {
+                    let tolerance = PIVOT_TOLERANCE * matrix[i][i].abs();
+                    lower[i][i] = if sum > tolerance { sum.sqrt() } else { 0.0 };
+                } else if lower[j][j] != 0.0 {
+                    lower[i][j] = sum / lower[j][j];
+                }
+            }
+        }
+        DenseCholesky { length, lower }
+    }
+
+    // null rows of the factor give zero in the solution.
+    pub fn solve(&self, rhs: &[f64]) -> Vec<f64> {
+        assert_eq!(self.length, rhs.len());
+        let l = &self.lower;
+        let mut result = rhs.to_vec();
+        for i in 0..self.length {
+            for k in 0..i {
+                result[i] -= l[i][k] * result[k];
+            }
+            result[i] = if l[i][i] != 0.0 {
+                result[i] / l[i][i]
+            } else {
+                0.0
+            };
+        }
+        for i in (0..self.length).rev() {
+            for k in i + 1..self.length {
+                result[i] -= l[k][i] * result[k];
+            }
+            result[i] = if l[i][i] != 0.0 {
+                result[i] / l[i][i]
+            } else {
+                0.0
+            };
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dense::DenseCholesky;
+
+    #[test]
+    fn test_solve() {
+        let m = vec![
+            vec![4.0, 2.0, 0.0],
+            vec![2.0, 5.0, 1.0],
+            vec![0.0, 1.0, 3.0],
+        ];
+        let factor = DenseCholesky::new(&m);
+        assert_eq!(factor.lower[0], vec![2.0, 0.0, 0.0]);
+        assert_eq!(factor.lower[1], vec![1.0, 2.0, 0.0]);
+        let x = factor.solve(&[6.0, 8.0, 4.0]);
+        for (i, row) in m.iter().enumerate() {
+            let product: f64 = row.iter().zip(x.iter()).map(|(a, b)| a * b).sum();
+            assert!((product - [6.0, 8.0, 4.0][i]).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn test_semidefinite() {
+        // singular with null space (1, 1).
+        let m = vec![vec![1.0, -1.0], vec![-1.0, 1.0]];
+        let factor = DenseCholesky::new(&m);
+        assert_eq!(factor.lower[1][1], 0.0);
+        let x = factor.solve(&[1.0, -1.0]);
+        assert_eq!(x, vec![1.0, 0.0]);
+    }
+}
@@ -0,0 +1,140 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use sparse_row_matrix::SparseRowMatrix;
+use linear_operator::LinearOperator;
+use dense::DenseCholesky;
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+
+// lower triangular sparsity pattern of the approximate inverse factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsaiPattern {
+    Matrix,
+    Squared,
+}
+
+// factorized sparse approximate inverse, M^-1 = G^T G with G lower
+// triangular and G A G^T close to the identity. applying it takes two
+// sparse mat-vecs and no triangular solves, so every row is independent.
+pub struct Fsai {
+    pub factor: SparseRowMatrix,
+    pub transpose: SparseRowMatrix,
+}
+
+impl Fsai {
+    pub fn new(matrix: &SparseSymmetricMatrix, pattern: FsaiPattern) -> Fsai {
+        let n = matrix.length + 1;
+        let mut rows = Vec::with_capacity(n);
+        for i in 0..n {
+            let columns = row_pattern(matrix, i, pattern);
+            // the local system A[J, J] y = e_i, with i the last entry of J.
+            let local = columns
+                .iter()
+                .map(|&r| columns.iter().map(|&c| entry(matrix, r, c)).collect())
+                .collect::<Vec<Vec<f64>>>();
+            let mut unit = vec![0.0; columns.len()];
+            unit[columns.len() - 1] = 1.0;
+            let y = DenseCholesky::new(&local).solve(&unit);
+            let pivot = y[columns.len() - 1];
+            // a null row of the matrix stays a null row of the factor.
+            let row = if pivot > 0.0 {
+                let scale = 1.0 / pivot.sqrt();
+                columns
+                    .iter()
+                    .zip(y.iter())
+                    .filter(|&(_, v)| *v != 0.0)
+                    .map(|(&j, v)| (j, v * scale))
+                    .collect()
+            } else {
+                vec![]
+            };
+            rows.push(row);
+        }
+        let factor = SparseRowMatrix::from_rows(&rows);
+        let transpose = factor.transpose();
+        Fsai { factor, transpose }
+    }
+}
+
+impl ApproximateInverse for Fsai {
+    fn apply(&self, v: &Vector) -> Vector {
+        let mut t = Vector(vec![0.0; v.0.len()]);
+        self.factor.apply_into(v, &mut t);
+        self.transpose.apply(&t)
+    }
+}
+
+// sorted columns j <= i in row i of the chosen pattern, ending with i.
+fn row_pattern(matrix: &SparseSymmetricMatrix, i: usize, pattern: FsaiPattern) -> Vec<usize> {
+    let mut columns = matrix.indices[i]
+        .iter()
+        .cloned()
+        .filter(|&j| j < i)
+        .collect::<Vec<usize>>();
+    if pattern == FsaiPattern::Squared {
+        for &k in &matrix.indices[i] {
+            columns.extend(matrix.indices[k].iter().cloned().filter(|&j| j < i));
+        }
+        columns.sort();
+        columns.dedup();
+    }
+    columns.push(i);
+    columns
+}
+
+fn entry(matrix: &SparseSymmetricMatrix, row: usize, column: usize) -> f64 {
+    match matrix.indices[row].binary_search(&column) {
+        Ok(k) => matrix.values[row][k],
+        Err(_) => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use approximate_inverse::{ApproximateInverse, Identity};
+    use test_matrices::{laplacian_2d, converged_iterations};
+    use fsai::{Fsai, FsaiPattern};
+
+    #[test]
+    fn test_diagonal() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 4.0 },
+            Entry { x: 1, y: 1, v: 16.0 },
+            Entry { x: 2, y: 2, v: 0.0 },
+        ]);
+        let fsai = Fsai::new(&m, FsaiPattern::Matrix);
+        let result = fsai.apply(&Vector(vec![1.0, 1.0, 1.0]));
+        assert_eq!(result.0, vec![0.25, 0.0625, 0.0]);
+    }
+
+    #[test]
+    fn test_unit_diagonal() {
+        // G A G^T has a unit diagonal by construction.
+        let m = laplacian_2d(6, 6);
+        let fsai = Fsai::new(&m, FsaiPattern::Matrix);
+        let srm = SparseRowMatrix::new(&m);
+        for i in 0..36 {
+            let mut unit = Vector(vec![0.0; 36]);
+            unit.0[i] = 1.0;
+            let column = fsai.transpose.apply(&unit);
+            let product = fsai.factor.apply(&srm.apply(&column));
+            assert!((product.0[i] - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_iterations() {
+        let m = laplacian_2d(24, 24);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 576]);
+        let plain = converged_iterations(&srm, &Identity, &v);
+        let matrix = converged_iterations(&srm, &Fsai::new(&m, FsaiPattern::Matrix), &v);
+        let squared = converged_iterations(&srm, &Fsai::new(&m, FsaiPattern::Squared), &v);
+        assert!(matrix < plain);
+        assert!(squared < matrix);
+    }
+}
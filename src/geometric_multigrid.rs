@@ -0,0 +1,168 @@
+use grid::{PressureGrid, Cell};
+use multigrid::{Multigrid, Level, CycleOptions, Prolongation, galerkin};
+
+#[derive(Debug, Clone)]
+pub struct GridMultigridOptions {
+    pub max_levels: usize,
+    // stop coarsening once a grid has at most this many cells.
+    pub max_coarse_size: usize,
+    pub cycle: CycleOptions,
+}
+
+impl Default for GridMultigridOptions {
+    fn default() -> GridMultigridOptions {
+        GridMultigridOptions {
+            max_levels: 16,
+            max_coarse_size: 64,
+            cycle: CycleOptions::default(),
+        }
+    }
+}
+
+// geometric multigrid for the pressure matrix of a grid, coarsening by
+// merging 2x2(x2) blocks of cells. the coarse matrices are galerkin
+// products, so solid walls and the free surface carry over to the coarse
+// levels.
+pub fn geometric_multigrid(grid: &PressureGrid, options: &GridMultigridOptions) -> Multigrid {
+    let mut levels = vec![];
+    let mut current = grid.clone();
+    let mut matrix = grid.matrix();
+    while levels.len() + 1 < options.max_levels && current.cells.len() > options.max_coarse_size {
+        let coarse = current.coarsen();
+        let prolongation = interpolation(&current, &coarse);
+        let coarse_matrix = galerkin(&matrix, &prolongation, coarse.cells.len());
+        levels.push(Level::new(matrix, prolongation));
+        matrix = coarse_matrix;
+        current = coarse;
+    }
+    levels.push(Level::new(matrix, vec![]));
+    Multigrid::new(levels, options.cycle.clone())
+}
+
+// cell centred bi- or trilinear interpolation from the coarse grid: along
+// each axis a fine cell takes 3/4 of the coarse cell covering it and 1/4 of
+// the next closest one. coarse cells that are not fluid are skipped and the
+// remaining weights rescaled, so constants are still interpolated exactly.
+fn interpolation(fine: &PressureGrid, coarse: &PressureGrid) -> Prolongation {
+    let axis = |i: usize, n: usize| {
+        let covering = i / 2;
+        let mut result = vec![(covering, 0.75)];
+        if i == 2 * covering {
+            if covering > 0 {
+                result.push((covering - 1, 0.25));
+            }
+        } else if covering + 1 < n {
+            result.push((covering + 1, 0.25));
+        }
+        result
+    };
+
+    let mut prolongation = vec![vec![]; fine.cells.len()];
+    for z in 0..fine.nz {
+        for y in 0..fine.ny {
+            for x in 0..fine.nx {
+                let i = fine.index(x, y, z);
+                if fine.cells[i] != Cell::Fluid {
+                    continue;
+                }
+                let mut row = vec![];
+                for &(cz, wz) in &axis(z, coarse.nz) {
+                    for &(cy, wy) in &axis(y, coarse.ny) {
+                        for &(cx, wx) in &axis(x, coarse.nx) {
+                            let j = coarse.index(cx, cy, cz);
+                            if coarse.cells[j] == Cell::Fluid {
+                                row.push((j, wx * wy * wz));
+                            }
+                        }
+                    }
+                }
+                let total = row.iter().fold(0.0, |acc, &(_, w)| acc + w);
+                for entry in &mut row {
+                    entry.1 /= total;
+                }
+                row.sort_by_key(|&(j, _)| j);
+                prolongation[i] = row;
+            }
+        }
+    }
+    prolongation
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use preconditioner::Preconditioner;
+    use grid::{PressureGrid, Cell};
+    use test_matrices::converged_iterations;
+    use geometric_multigrid::{geometric_multigrid, interpolation, GridMultigridOptions};
+
+    // a tank with solid walls and floor, air above the water line and a
+    // solid block in the middle.
+    fn tank(n: usize) -> PressureGrid {
+        let mut cells = vec![];
+        for y in 0..n {
+            for x in 0..n {
+                let wall = x == 0 || x + 1 == n || y == 0;
+                let block = x > n / 3 && x < n / 2 && y > n / 4 && y < n / 2;
+                cells.push(if wall || block {
+                    Cell::Solid
+                } else if y > 3 * n / 4 {
+                    Cell::Air
+                } else {
+                    Cell::Fluid
+                });
+            }
+        }
+        PressureGrid::new(n, n, 1, cells)
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let grid = tank(64);
+        let multigrid = geometric_multigrid(&grid, &GridMultigridOptions::default());
+        let sizes = multigrid.levels.iter().map(|l| l.len()).collect::<Vec<usize>>();
+        assert_eq!(sizes, vec![4096, 1024, 256, 64]);
+
+        let mut cells = vec![Cell::Fluid; 7 * 7 * 7];
+        for cell in cells.iter_mut().skip(6 * 49) {
+            *cell = Cell::Air;
+        }
+        let grid = PressureGrid::new(7, 7, 7, cells);
+        let multigrid = geometric_multigrid(&grid, &GridMultigridOptions::default());
+        let sizes = multigrid.levels.iter().map(|l| l.len()).collect::<Vec<usize>>();
+        assert_eq!(sizes, vec![343, 64]);
+    }
+
+    #[test]
+    fn test_interpolates_constants() {
+        let grid = tank(16);
+        let coarse = grid.coarsen();
+        let prolongation = interpolation(&grid, &coarse);
+        for (i, row) in prolongation.iter().enumerate() {
+            let total = row.iter().fold(0.0, |acc, &(_, w)| acc + w);
+            if grid.cells[i] == Cell::Fluid {
+                assert!((total - 1.0).abs() < 1e-15);
+                assert!(row.iter().all(|&(j, _)| coarse.cells[j] == Cell::Fluid));
+            } else {
+                assert!(row.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_fewer_iterations_than_mic() {
+        let grid = tank(64);
+        let m = grid.matrix();
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(
+            grid.cells
+                .iter()
+                .map(|&c| if c == Cell::Fluid { 1.0 } else { 0.0 })
+                .collect(),
+        );
+        let mic = converged_iterations(&srm, &Preconditioner::new(&m), &v);
+        let multigrid = geometric_multigrid(&grid, &GridMultigridOptions::default());
+        assert!(3 * converged_iterations(&srm, &multigrid, &v) < mic);
+    }
+}
@@ -0,0 +1,245 @@
+use std::vec::Vec;
+
+use vector::Vector;
+use linear_operator::LinearOperator;
+use approximate_inverse::ApproximateInverse;
+use solver::{SolverResult, SolverOptions, StopReason};
+
+// restarted GMRES(m) for general, nonsymmetric, systems. the krylov basis
+// is built with modified gram-schmidt and the least squares problem is kept
+// triangular with givens rotations. the preconditioner is applied from the
+// right, so the residual that is minimized is the true one. its euclidean
+// norm is what gmres monitors, so that norm is used whatever the options
+// ask for. the history holds the estimates of the inner iterations.
+pub fn solve_gmres<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+    x0: &Vector,
+    restart: usize,
+    options: &SolverOptions,
+) -> SolverResult {
+    let n = a.dimension();
+    options.assert_no_null_space();
+    assert_eq!(n, rhs.0.len());
+    assert_eq!(n, x0.0.len());
+    assert!(restart > 0);
+    if rhs.largest_absolute_value() == 0.0 {
+        return SolverResult::zero_rhs(rhs, options);
+    }
+    let tolerance = options.tolerance(rhs.norm());
+
+    let mut result = x0.clone();
+    let mut q = Vector(vec![0.0; n]);
+    a.apply_into(&result, &mut q);
+    let mut r = rhs.clone() + q.scale(-1.0);
+    let initial_residual = r.norm();
+    let mut history = if options.record_history {
+        Some(vec![initial_residual])
+    } else {
+        None
+    };
+    let mut residual = initial_residual;
+    let mut iterations = 0;
+
+    while residual > tolerance && iterations < options.max_iterations {
+        let mut basis = vec![r.scale(1.0 / residual)];
+        // the rotated hessenberg matrix by column, and the rotated rhs.
+        let mut columns: Vec<Vec<f64>> = vec![];
+        let mut rotations: Vec<(f64, f64)> = vec![];
+        let mut g = vec![residual];
+        let mut breakdown = false;
+
+        while columns.len() < restart && iterations < options.max_iterations {
+            let j = columns.len();
+            a.apply_into(&preconditioner.apply(&basis[j]), &mut q);
+            let mut w = q.clone();
+            let mut h = Vec::with_capacity(j + 2);
+            for v in &basis {
+                let projection = w.dot(v);
+                w = w + v.scale(-projection);
+                h.push(projection);
+            }
+            let norm = w.norm();
+            h.push(norm);
+
+            for (i, &(c, s)) in rotations.iter().enumerate() {
+                let upper = c * h[i] + s * h[i + 1];
+                h[i + 1] = c * h[i + 1] - s * h[i];
+                h[i] = upper;
+            }
+            let gamma = h[j].hypot(h[j + 1]);
+            if !gamma.is_normal() {
+                // A M^-1 maps the new basis vector into the span of the
+                // previous ones. the columns so far still give the best
+                // iterate available.
+                breakdown = true;
+                break;
+            }
+            let (c, s) = (h[j] / gamma, h[j + 1] / gamma);
+            h[j] = gamma;
+            h.pop();
+            rotations.push((c, s));
+            g.push(-s * g[j]);
+            g[j] *= c;
+            columns.push(h);
+
+            iterations += 1;
+            let estimate = g[j + 1].abs();
+            if let Some(ref mut history) = history {
+                history.push(estimate);
+            }
+            // a zero norm means the krylov space is invariant and the
+            // solution is exact.
+            if estimate <= tolerance || norm == 0.0 {
+                break;
+            }
+            basis.push(w.scale(1.0 / norm));
+        }
+
+        // back substitution for the coefficients of the basis vectors.
+        let k = columns.len();
+        let mut y = vec![0.0; k];
+        for i in (0..k).rev() {
+            let sum = (i + 1..k).fold(g[i], |acc, l| acc - columns[l][i] * y[l]);
+            y[i] = sum / columns[i][i];
+        }
+        let update = basis
+            .iter()
+            .zip(y.iter())
+            .fold(Vector(vec![0.0; n]), |acc, (v, &c)| acc + v.scale(c));
+        result = result + preconditioner.apply(&update);
+        a.apply_into(&result, &mut q);
+        r = rhs.clone() + q.scale(-1.0);
+        residual = r.norm();
+        if breakdown && residual > tolerance {
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iterations,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+    }
+
+    let stop_reason = if residual <= tolerance {
+        StopReason::Converged
+    } else {
+        StopReason::MaxIterations
+    };
+    SolverResult::new(
+        stop_reason,
+        iterations,
+        result,
+        r,
+        initial_residual,
+        residual,
+        history,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::Entry;
+    use sparse_row_matrix::SparseRowMatrix;
+    use approximate_inverse::Identity;
+    use jacobi::Jacobi;
+    use vector::Vector;
+    use solver::{SolverOptions, StopReason};
+    use test_matrices::{convection_diffusion, assert_true_residual};
+    use gmres::solve_gmres;
+
+    #[test]
+    fn test_full_gmres() {
+        // without restarts gmres is exact after n steps.
+        let a = SparseRowMatrix::from_entries(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 0, y: 1, v: 2.0 },
+            Entry { x: 1, y: 0, v: -3.0 },
+            Entry { x: 1, y: 2, v: 1.0 },
+            Entry { x: 2, y: 1, v: 4.0 },
+            Entry { x: 2, y: 2, v: 1.0 },
+        ]);
+        let v = Vector(vec![1.0, 2.0, 3.0]);
+        let x0 = Vector(vec![0.0; 3]);
+        let options = SolverOptions::new().relative_tolerance(1e-12);
+        let result = solve_gmres(&a, &Identity, &v, &x0, 3, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert!(result.iterations <= 3);
+        assert_true_residual(&a, &v, &result.best_guess, &result.residual);
+        assert!(result.final_residual_norm < 1e-12 * v.norm());
+    }
+
+    #[test]
+    fn test_restarted() {
+        let a = convection_diffusion(16, 2.0, &[1.0; 256]);
+        let v = Vector((0..256).map(|i| ((i * 3) % 5) as f64).collect());
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(1000)
+            .record_history(true);
+        let short = solve_gmres(&a, &Identity, &v, &x0, 5, &options);
+        let long = solve_gmres(&a, &Identity, &v, &x0, 40, &options);
+        assert_eq!(short.stop_reason, StopReason::Converged);
+        assert_eq!(long.stop_reason, StopReason::Converged);
+        assert!(long.iterations < short.iterations);
+        assert_true_residual(&a, &v, &long.best_guess, &long.residual);
+        assert_eq!(long.residual_history.unwrap().len(), long.iterations + 1);
+    }
+
+    #[test]
+    fn test_preconditioned() {
+        let scale = (0..256).map(|i| 1.0 + (i % 17) as f64).collect::<Vec<f64>>();
+        let a = convection_diffusion(16, 10.0, &scale);
+        let v = Vector(vec![1.0; 256]);
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(1000);
+        let plain = solve_gmres(&a, &Identity, &v, &x0, 20, &options);
+        let jacobi = Jacobi {
+            inverse_diagonals: scale.iter().map(|s| 1.0 / (14.0 * s)).collect(),
+        };
+        let preconditioned = solve_gmres(&a, &jacobi, &v, &x0, 20, &options);
+        assert_eq!(preconditioned.stop_reason, StopReason::Converged);
+        assert!(preconditioned.iterations < plain.iterations);
+        assert_true_residual(&a, &v, &preconditioned.best_guess, &preconditioned.residual);
+    }
+
+    #[test]
+    fn test_breakdown() {
+        // singular, the second basis vector maps into the span of the first.
+        let a = SparseRowMatrix::from_entries(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 1, y: 1, v: 1.0 },
+            Entry { x: 3, y: 3, v: 0.0 },
+        ]);
+        let v = Vector(vec![1.0; 4]);
+        let x0 = Vector(vec![0.0; 4]);
+        let result = solve_gmres(&a, &Identity, &v, &x0, 4, &SolverOptions::new());
+        assert_eq!(result.stop_reason, StopReason::Breakdown);
+        assert_eq!(result.iterations, 1);
+        // the least squares solution over the first basis vector.
+        assert_true_residual(&a, &v, &result.best_guess, &result.residual);
+        assert!((result.best_guess.0[0] - 1.0).abs() < 1e-12);
+        assert!((result.final_residual_norm - 2.0f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_max_iterations() {
+        let a = convection_diffusion(16, 2.0, &[1.0; 256]);
+        let v = Vector(vec![1.0; 256]);
+        let x0 = Vector(vec![0.0; 256]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-12)
+            .max_iterations(7);
+        let result = solve_gmres(&a, &Identity, &v, &x0, 3, &options);
+        assert_eq!(result.stop_reason, StopReason::MaxIterations);
+        assert_eq!(result.iterations, 7);
+        assert!(result.final_residual_norm < result.initial_residual_norm);
+    }
+}
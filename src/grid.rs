@@ -0,0 +1,156 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cell {
+    Fluid,
+    // a wall, pressure has zero normal derivative across it.
+    Solid,
+    // free surface, pressure is zero.
+    Air,
+}
+
+// a regular 2d (nz = 1) or 3d grid of cells as used for fluid pressure
+// projection, see Robert Bridson, "Fluid Simulation for Computer Graphics".
+// cell (x, y, z) has index (z ny + y) nx + x.
+#[derive(Debug, Clone)]
+pub struct PressureGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub cells: Vec<Cell>,
+}
+
+impl PressureGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, cells: Vec<Cell>) -> PressureGrid {
+        assert!(nx > 0 && ny > 0 && nz > 0);
+        assert_eq!(nx * ny * nz, cells.len());
+        PressureGrid { nx, ny, nz, cells }
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.ny + y) * self.nx + x
+    }
+
+    // the neighbours of a cell in the positive x, y and z directions.
+    fn forward_neighbours(&self, x: usize, y: usize, z: usize) -> Vec<usize> {
+        let mut result = vec![];
+        if x + 1 < self.nx {
+            result.push(self.index(x + 1, y, z));
+        }
+        if y + 1 < self.ny {
+            result.push(self.index(x, y + 1, z));
+        }
+        if z + 1 < self.nz {
+            result.push(self.index(x, y, z + 1));
+        }
+        result
+    }
+
+    // the unscaled 5 (or 7) point laplacian over the fluid cells, one
+    // unknown per cell. the outside of the grid is solid and every cell that
+    // is not fluid is a null row and column.
+    pub fn matrix(&self) -> SparseSymmetricMatrix {
+        let mut diagonals = vec![0.0; self.cells.len()];
+        let mut entries = vec![];
+        for z in 0..self.nz {
+            for y in 0..self.ny {
+                for x in 0..self.nx {
+                    let i = self.index(x, y, z);
+                    for j in self.forward_neighbours(x, y, z) {
+                        match (self.cells[i], self.cells[j]) {
+                            (Cell::Fluid, Cell::Fluid) => {
+                                diagonals[i] += 1.0;
+                                diagonals[j] += 1.0;
+                                entries.push(Entry { x: i, y: j, v: -1.0 });
+                            }
+                            (Cell::Fluid, Cell::Air) => diagonals[i] += 1.0,
+                            (Cell::Air, Cell::Fluid) => diagonals[j] += 1.0,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        for (i, &v) in diagonals.iter().enumerate() {
+            entries.push(Entry { x: i, y: i, v });
+        }
+        SparseSymmetricMatrix::new(&entries)
+    }
+
+    // the grid with cells twice as large. a coarse cell is fluid if any of
+    // the cells it covers is fluid, otherwise air if any of them is air.
+    pub fn coarsen(&self) -> PressureGrid {
+        let nx = self.nx / 2 + self.nx % 2;
+        let ny = self.ny / 2 + self.ny % 2;
+        let nz = self.nz / 2 + self.nz % 2;
+        let mut cells = vec![Cell::Solid; nx * ny * nz];
+        for z in 0..self.nz {
+            for y in 0..self.ny {
+                for x in 0..self.nx {
+                    let parent = &mut cells[((z / 2) * ny + y / 2) * nx + x / 2];
+                    match self.cells[self.index(x, y, z)] {
+                        Cell::Fluid => *parent = Cell::Fluid,
+                        Cell::Air if *parent == Cell::Solid => *parent = Cell::Air,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        PressureGrid { nx, ny, nz, cells }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid::{PressureGrid, Cell};
+
+    #[test]
+    fn test_matrix() {
+        // fluid, fluid, air over a solid row.
+        let grid = PressureGrid::new(
+            3,
+            2,
+            1,
+            vec![
+                Cell::Fluid,
+                Cell::Fluid,
+                Cell::Air,
+                Cell::Solid,
+                Cell::Solid,
+                Cell::Solid,
+            ],
+        );
+        let m = grid.matrix();
+        assert_eq!(m.length, 5);
+        assert_eq!(m.indices[0], vec![0, 1]);
+        assert_eq!(m.values[0], vec![1.0, -1.0]);
+        assert_eq!(m.indices[1], vec![0, 1]);
+        assert_eq!(m.values[1], vec![-1.0, 2.0]);
+        for i in 2..6 {
+            assert_eq!(m.indices[i], vec![i]);
+            assert_eq!(m.values[i], vec![0.0]);
+        }
+    }
+
+    #[test]
+    fn test_coarsen() {
+        let grid = PressureGrid::new(
+            3,
+            2,
+            1,
+            vec![
+                Cell::Solid,
+                Cell::Air,
+                Cell::Solid,
+                Cell::Fluid,
+                Cell::Solid,
+                Cell::Air,
+            ],
+        );
+        let coarse = grid.coarsen();
+        assert_eq!((coarse.nx, coarse.ny, coarse.nz), (2, 1, 1));
+        assert_eq!(coarse.cells, vec![Cell::Fluid, Cell::Air]);
+    }
+}
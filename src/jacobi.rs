@@ -0,0 +1,96 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+
+// diagonal preconditioner, cheap to build and a good fit for strongly
+// diagonally dominant matrices.
+pub struct Jacobi {
+    pub inverse_diagonals: Vec<f64>,
+}
+
+impl Jacobi {
+    pub fn new(matrix: &SparseSymmetricMatrix) -> Jacobi {
+        let mut inverse_diagonals = vec![0.0; matrix.length + 1];
+        for (i, inverse) in inverse_diagonals.iter_mut().enumerate() {
+            for (j, &index) in matrix.indices[i].iter().enumerate() {
+                let value = matrix.values[i][j];
+                // a zero diagonal is a null row and column.
+                if index == i && value != 0.0 {
+                    *inverse = 1.0 / value;
+                }
+            }
+        }
+        Jacobi { inverse_diagonals }
+    }
+}
+
+impl ApproximateInverse for Jacobi {
+    fn apply(&self, v: &Vector) -> Vector {
+        assert_eq!(self.inverse_diagonals.len(), v.0.len());
+        Vector(
+            v.0
+                .iter()
+                .zip(self.inverse_diagonals.iter())
+                .map(|(x, d)| x * d)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use approximate_inverse::ApproximateInverse;
+    use solver::{solve_operator, SolverOptions, StopReason};
+    use jacobi::Jacobi;
+
+    #[test]
+    fn test_construct() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 4.0 },
+            Entry { x: 0, y: 1, v: 1.0 },
+            Entry { x: 1, y: 1, v: 0.0 },
+            Entry { x: 2, y: 2, v: 0.5 },
+        ]);
+        let jacobi = Jacobi::new(&m);
+        assert_eq!(jacobi.inverse_diagonals, vec![0.25, 0.0, 2.0]);
+        let result = jacobi.apply(&Vector(vec![1.0, 1.0, 1.0]));
+        assert_eq!(result.0, vec![0.25, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_solve() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry {
+                x: 0,
+                y: 0,
+                v: 10.0,
+            },
+            Entry { x: 1, y: 0, v: 1.0 },
+            Entry {
+                x: 1,
+                y: 1,
+                v: 20.0,
+            },
+            Entry { x: 2, y: 1, v: 2.0 },
+            Entry {
+                x: 2,
+                y: 2,
+                v: 30.0,
+            },
+        ]);
+        let v = Vector(vec![11.0, 23.0, 32.0]);
+        let x0 = Vector(vec![0.0; 3]);
+        let options = SolverOptions::new().relative_tolerance(1e-12);
+        let srm = SparseRowMatrix::new(&m);
+        let result = solve_operator(&srm, &Jacobi::new(&m), &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        for x in &result.best_guess.0 {
+            assert!((x - 1.0).abs() < 1e-10);
+        }
+    }
+}
@@ -2,5 +2,24 @@ pub mod sparse_symmetric_matrix;
 pub mod sparse_row_matrix;
 pub mod vector;
 pub mod preconditioner;
+pub mod approximate_inverse;
+pub mod jacobi;
+pub mod ssor;
+pub mod dense;
+pub mod multigrid;
+pub mod amg;
+pub mod grid;
+pub mod geometric_multigrid;
+pub mod chebyshev;
+pub mod fsai;
+pub mod block_jacobi;
+pub mod deflation;
+pub mod multi_shift;
+pub mod minres;
+pub mod gmres;
+pub mod bicgstab;
 pub mod solver;
 pub mod validity;
+pub mod linear_operator;
+#[cfg(test)]
+mod test_matrices;
@@ -0,0 +1,9 @@
+use vector::Vector;
+
+// trait for anything that can be multiplied with a vector. the solver only
+// ever needs products, so operators never have to be assembled.
+pub trait LinearOperator {
+    fn dimension(&self) -> usize;
+    // overwrite result with the product of this operator and v.
+    fn apply_into(&self, v: &Vector, result: &mut Vector);
+}
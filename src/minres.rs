@@ -0,0 +1,289 @@
+use vector::Vector;
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use sparse_row_matrix::SparseRowMatrix;
+use linear_operator::LinearOperator;
+use approximate_inverse::{ApproximateInverse, Identity};
+use solver::{SolverResult, SolverOptions, StopReason, ResidualNorm};
+
+// MINRES for symmetric systems that need not be positive definite, without
+// preconditioning. the incomplete cholesky factor does not apply here, as it
+// needs a positive definite matrix.
+pub fn minres(m: &SparseSymmetricMatrix, rhs: &Vector, options: &SolverOptions) -> SolverResult {
+    let srm = SparseRowMatrix::new(m);
+    let x0 = Vector(vec![0.0; rhs.0.len()]);
+    solve_minres(&srm, &Identity, rhs, &x0, options)
+}
+
+// preconditioned MINRES after Paige and Saunders, minimizing the residual in
+// the norm of the inverse preconditioner over the krylov space. the matrix
+// only has to be symmetric, the preconditioner must be symmetric positive
+// definite; a breakdown is reported if it turns out not to be. besides the
+// solution, the product A w of every update direction is kept, so that the
+// residual vector is available without extra products.
+pub fn solve_minres<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    let n = a.dimension();
+    options.assert_no_null_space();
+    assert_eq!(n, rhs.0.len());
+    assert_eq!(n, x0.0.len());
+    if rhs.largest_absolute_value() == 0.0 {
+        return SolverResult::zero_rhs(rhs, options);
+    }
+
+    let rhs_norm = match options.residual_norm {
+        ResidualNorm::Preconditioned => {
+            options.measure(rhs, preconditioner.apply(rhs).dot(rhs))
+        }
+        _ => options.measure(rhs, 0.0),
+    };
+    let tolerance = options.tolerance(rhs_norm);
+
+    let mut result = x0.clone();
+    let mut q = Vector(vec![0.0; n]);
+    a.apply_into(&result, &mut q);
+    let mut r = rhs.clone() + q.scale(-1.0);
+    let mut y = preconditioner.apply(&r);
+    let rho = y.dot(&r);
+    let initial_residual = options.measure(&r, rho);
+    let mut history = if options.record_history {
+        Some(vec![initial_residual])
+    } else {
+        None
+    };
+    if initial_residual <= tolerance {
+        return SolverResult::new(
+            StopReason::Converged,
+            0,
+            result,
+            r,
+            initial_residual,
+            initial_residual,
+            history,
+        );
+    }
+    if !rho.is_normal() || rho < 0.0 {
+        return SolverResult::new(
+            StopReason::NonNormalRho,
+            0,
+            result,
+            r,
+            initial_residual,
+            initial_residual,
+            history,
+        );
+    }
+
+    // the last two lanczos vectors, before preconditioning.
+    let mut r1 = r.clone();
+    let mut r2 = r.clone();
+    let mut beta = rho.sqrt();
+    let mut old_beta = 0.0;
+    let mut phi_bar = beta;
+    // the previous plane rotation and the entries it leaves behind.
+    let mut cs = -1.0;
+    let mut sn = 0.0;
+    let mut delta_bar = 0.0;
+    let mut epsilon = 0.0;
+    // the last three update directions and their products with A.
+    let mut w = Vector(vec![0.0; n]);
+    let mut w1;
+    let mut w2 = Vector(vec![0.0; n]);
+    let mut aw = Vector(vec![0.0; n]);
+    let mut aw1;
+    let mut aw2 = Vector(vec![0.0; n]);
+    let mut residual = initial_residual;
+
+    for iteration in 0..options.max_iterations {
+        // lanczos step.
+        let v = y.scale(1.0 / beta);
+        a.apply_into(&v, &mut q);
+        let av = q.clone();
+        if iteration > 0 {
+            q = q + r1.scale(-beta / old_beta);
+        }
+        let alpha = v.dot(&q);
+        q = q + r2.scale(-alpha / beta);
+        r1 = r2;
+        r2 = q.clone();
+        y = preconditioner.apply(&r2);
+        old_beta = beta;
+        let beta_squared = r2.dot(&y);
+        if beta_squared < 0.0 {
+            // the preconditioner is not positive definite.
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        beta = beta_squared.sqrt();
+
+        // apply the previous rotation, then eliminate the new subdiagonal.
+        let old_epsilon = epsilon;
+        let delta = cs * delta_bar + sn * alpha;
+        let gamma_bar = sn * delta_bar - cs * alpha;
+        epsilon = sn * beta;
+        delta_bar = -cs * beta;
+        let gamma = gamma_bar.hypot(beta);
+        if !gamma.is_normal() {
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        cs = gamma_bar / gamma;
+        sn = beta / gamma;
+        let phi = cs * phi_bar;
+        phi_bar *= sn;
+
+        w1 = w2;
+        w2 = w;
+        aw1 = aw2;
+        aw2 = aw;
+        w = (v + w1.scale(-old_epsilon) + w2.scale(-delta)).scale(1.0 / gamma);
+        aw = (av + aw1.scale(-old_epsilon) + aw2.scale(-delta)).scale(1.0 / gamma);
+        result = result + w.scale(phi);
+        r = r + aw.scale(-phi);
+        // phi_bar is the norm of the residual in the inverse preconditioner.
+        residual = options.measure(&r, phi_bar * phi_bar);
+        if let Some(ref mut history) = history {
+            history.push(residual);
+        }
+        if residual <= tolerance {
+            return SolverResult::new(
+                StopReason::Converged,
+                iteration + 1,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        if !beta.is_normal() {
+            // the krylov space is invariant, but the system was not solved.
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration + 1,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+    }
+
+    SolverResult::new(
+        StopReason::MaxIterations,
+        options.max_iterations,
+        result,
+        r,
+        initial_residual,
+        residual,
+        history,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use jacobi::Jacobi;
+    use vector::Vector;
+    use solver::{SolverOptions, StopReason, ResidualNorm};
+    use test_matrices::{laplacian_2d, neumann, shift_diagonal, assert_true_residual};
+    use minres::{minres, solve_minres};
+
+    #[test]
+    fn test_saddle_point() {
+        // [[A, B^T], [B, 0]] with A = diag(2, 3, 4) and B = [1, 1, 1].
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 2.0 },
+            Entry { x: 1, y: 1, v: 3.0 },
+            Entry { x: 2, y: 2, v: 4.0 },
+            Entry { x: 0, y: 3, v: 1.0 },
+            Entry { x: 1, y: 3, v: 1.0 },
+            Entry { x: 2, y: 3, v: 1.0 },
+            Entry { x: 3, y: 3, v: 0.0 },
+        ]);
+        let v = Vector(vec![1.0, 2.0, 3.0, 4.0]);
+        let result = minres(&m, &v, &SolverOptions::new().relative_tolerance(1e-12));
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert!(result.iterations <= 4);
+        assert_true_residual(&m, &v, &result.best_guess, &result.residual);
+        assert!(result.residual.largest_absolute_value() < 1e-10);
+    }
+
+    #[test]
+    fn test_indefinite() {
+        let m = shift_diagonal(&laplacian_2d(12, 12), -1.0);
+        let v = Vector((0..144).map(|i| ((i * 3) % 7) as f64 - 3.0).collect());
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(500)
+            .residual_norm(ResidualNorm::L2)
+            .record_history(true);
+        let result = minres(&m, &v, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert_true_residual(&m, &v, &result.best_guess, &result.residual);
+        // the euclidean residual norm never increases.
+        let history = result.residual_history.unwrap();
+        for pair in history.windows(2) {
+            assert!(pair[1] <= pair[0] * (1.0 + 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_preconditioned() {
+        let m = shift_diagonal(&laplacian_2d(12, 12), -1.0);
+        let jacobi = Jacobi::new(&laplacian_2d(12, 12));
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector((0..144).map(|i| ((i * 3) % 7) as f64 - 3.0).collect());
+        let x0 = Vector(vec![1.0; 144]);
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .max_iterations(500);
+        let result = solve_minres(&srm, &jacobi, &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert_true_residual(&m, &v, &result.best_guess, &result.residual);
+    }
+
+    #[test]
+    fn test_indefinite_preconditioner() {
+        let m = shift_diagonal(&laplacian_2d(4, 4), -1.0);
+        let negative = shift_diagonal(&laplacian_2d(4, 4), -8.0);
+        let srm = SparseRowMatrix::new(&m);
+        let mut v = Vector(vec![0.0; 16]);
+        v.0[0] = 1.0;
+        let x0 = Vector(vec![0.0; 16]);
+        let result = solve_minres(&srm, &Jacobi::new(&negative), &v, &x0, &SolverOptions::new());
+        assert_eq!(result.stop_reason, StopReason::NonNormalRho);
+        let mut mixed = shift_diagonal(&laplacian_2d(4, 4), -1.0);
+        mixed.values[5][2] = -1.0;
+        let v = Vector((0..16).map(|i| i as f64).collect());
+        let result = solve_minres(&srm, &Jacobi::new(&mixed), &v, &x0, &SolverOptions::new());
+        assert_eq!(result.stop_reason, StopReason::Breakdown);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_null_space() {
+        let options = SolverOptions::new().null_space(vec![Vector(vec![1.0; 16])]);
+        minres(&neumann(4), &Vector(vec![1.0; 16]), &options);
+    }
+}
@@ -0,0 +1,205 @@
+use std::vec::Vec;
+
+use vector::Vector;
+use linear_operator::LinearOperator;
+use solver::{SolverResult, SolverOptions, StopReason};
+
+// the state of one shifted system. its residual is always a multiple zeta of
+// the seed residual, so only the solution and search direction are kept.
+struct Shifted {
+    delta: f64,
+    zeta: f64,
+    zeta_previous: f64,
+    solution: Vector,
+    direction: Vector,
+    residual: f64,
+    history: Option<Vec<f64>>,
+    stop: Option<(StopReason, usize)>,
+    // the residual at the time of convergence.
+    final_residual: Option<Vector>,
+}
+
+// solve (A + shift I) x = b for all shifts at once from a single krylov
+// sequence, the one of the smallest shift. every shifted matrix has to be
+// positive definite. there is no preconditioning, as a preconditioner would
+// destroy the shift invariance of the krylov space. a null space of A is
+// not one of the shifted matrices, so none can be projected out. returns one
+// result per shift, in the order of the shifts; the residuals are the
+// recursively updated ones.
+pub fn solve_shifted<A: LinearOperator>(
+    a: &A,
+    shifts: &[f64],
+    rhs: &Vector,
+    options: &SolverOptions,
+) -> Vec<SolverResult> {
+    let n = a.dimension();
+    options.assert_no_null_space();
+    assert_eq!(n, rhs.0.len());
+    assert!(shifts.iter().all(|s| s.is_finite()));
+    if rhs.largest_absolute_value() == 0.0 {
+        return shifts
+            .iter()
+            .map(|_| SolverResult::zero_rhs(rhs, options))
+            .collect();
+    }
+    if shifts.is_empty() {
+        return vec![];
+    }
+    let seed = shifts.iter().cloned().fold(f64::INFINITY, f64::min);
+
+    let mut r = rhs.clone();
+    let mut rho = r.dot(&r);
+    let initial_residual = options.measure(&r, rho);
+    let tolerance = options.tolerance(initial_residual);
+    let mut systems = shifts
+        .iter()
+        .map(|&shift| {
+            Shifted {
+                delta: shift - seed,
+                zeta: 1.0,
+                zeta_previous: 1.0,
+                solution: Vector(vec![0.0; n]),
+                direction: rhs.clone(),
+                residual: initial_residual,
+                history: if options.record_history {
+                    Some(vec![initial_residual])
+                } else {
+                    None
+                },
+                stop: None,
+                final_residual: None,
+            }
+        })
+        .collect::<Vec<Shifted>>();
+
+    let mut p = rhs.clone();
+    let mut q = Vector(vec![0.0; n]);
+    let mut alpha_previous = 1.0;
+    let mut beta_previous = 0.0;
+    for iteration in 0..options.max_iterations {
+        a.apply_into(&p, &mut q);
+        q = q + p.scale(seed);
+        let curvature = p.dot(&q);
+        if !curvature.is_normal() {
+            for system in systems.iter_mut().filter(|s| s.stop.is_none()) {
+                system.stop = Some((StopReason::Breakdown, iteration));
+            }
+            break;
+        }
+        let alpha = rho / curvature;
+        r = r + q.scale(-alpha);
+        let rho_new = r.dot(&r);
+        let residual = options.measure(&r, rho_new);
+        let beta = rho_new / rho;
+
+        for system in systems.iter_mut().filter(|s| s.stop.is_none()) {
+            let zeta_next = system.zeta * system.zeta_previous * alpha_previous /
+                (alpha * beta_previous * (system.zeta_previous - system.zeta) +
+                     system.zeta_previous * alpha_previous * (1.0 + system.delta * alpha));
+            let ratio = zeta_next / system.zeta;
+            system.solution = system.solution.clone() + system.direction.scale(alpha * ratio);
+            system.zeta_previous = system.zeta;
+            system.zeta = zeta_next;
+            system.residual = zeta_next.abs() * residual;
+            if let Some(ref mut history) = system.history {
+                history.push(system.residual);
+            }
+            if system.residual <= tolerance {
+                system.stop = Some((StopReason::Converged, iteration + 1));
+                system.final_residual = Some(r.scale(zeta_next));
+            } else {
+                system.direction = r.scale(zeta_next) +
+                    system.direction.scale(ratio * ratio * beta);
+            }
+        }
+        if systems.iter().all(|s| s.stop.is_some()) {
+            break;
+        }
+
+        p = r.clone() + p.scale(beta);
+        rho = rho_new;
+        alpha_previous = alpha;
+        beta_previous = beta;
+    }
+
+    systems
+        .into_iter()
+        .map(|system| {
+            let (stop_reason, iterations) = system.stop.unwrap_or((
+                StopReason::MaxIterations,
+                options.max_iterations,
+            ));
+            let zeta = system.zeta;
+            let residual = system.final_residual.unwrap_or_else(|| r.scale(zeta));
+            SolverResult::new(
+                stop_reason,
+                iterations,
+                system.solution,
+                residual,
+                initial_residual,
+                system.residual,
+                system.history,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_row_matrix::SparseRowMatrix;
+    use approximate_inverse::Identity;
+    use vector::Vector;
+    use solver::{solve_operator, SolverOptions, StopReason};
+    use test_matrices::{laplacian_2d, shift_diagonal, assert_true_residual};
+    use multi_shift::solve_shifted;
+
+    #[test]
+    fn test_shifted_solutions() {
+        let m = laplacian_2d(12, 12);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector((0..144).map(|i| ((i * 5) % 9) as f64 - 4.0).collect());
+        let shifts = [0.5, -0.2, 2.0, 10.0];
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-10)
+            .record_history(true);
+        let results = solve_shifted(&srm, &shifts, &v, &options);
+        assert_eq!(results.len(), 4);
+        for (&shift, result) in shifts.iter().zip(results.iter()) {
+            assert_eq!(result.stop_reason, StopReason::Converged);
+            // compare with the true residual of the shifted system.
+            let shifted = shift_diagonal(&m, shift);
+            assert_true_residual(&shifted, &v, &result.best_guess, &result.residual);
+            assert!(result.residual.largest_absolute_value() < 1e-8);
+            let history = result.residual_history.as_ref().unwrap();
+            assert_eq!(history.len(), result.iterations + 1);
+        }
+        // larger shifts are better conditioned and converge first.
+        assert!(results[3].iterations < results[2].iterations);
+        assert!(results[2].iterations < results[0].iterations);
+        assert!(results[0].iterations <= results[1].iterations);
+
+        // the seed system follows plain cg.
+        let x0 = Vector(vec![0.0; 144]);
+        let srm = SparseRowMatrix::new(&shift_diagonal(&m, -0.2));
+        let plain = solve_operator(&srm, &Identity, &v, &x0, &options);
+        assert_eq!(plain.iterations, results[1].iterations);
+    }
+
+    #[test]
+    fn test_zero_rhs() {
+        let srm = SparseRowMatrix::new(&laplacian_2d(3, 3));
+        let zero = Vector(vec![0.0; 9]);
+        let results = solve_shifted(&srm, &[0.0, 1.0], &zero, &SolverOptions::new());
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert_eq!(result.stop_reason, StopReason::ZeroRhs);
+        }
+    }
+
+    #[test]
+    fn test_no_shifts() {
+        let srm = SparseRowMatrix::new(&laplacian_2d(3, 3));
+        let v = Vector(vec![1.0; 9]);
+        assert!(solve_shifted(&srm, &[], &v, &SolverOptions::new()).is_empty());
+    }
+}
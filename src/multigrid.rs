@@ -0,0 +1,272 @@
+use std::vec::Vec;
+use std::collections::BTreeMap;
+
+use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+use dense::DenseCholesky;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoother {
+    // damped jacobi with the given weight.
+    Jacobi(f64),
+    // forward sweeps before and backward sweeps after the coarse grid
+    // correction, which keeps the cycle symmetric.
+    GaussSeidel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoarseSolve {
+    // dense cholesky factorization of the coarsest matrix.
+    Direct,
+    // only the given number of smoothing sweeps.
+    Smooth(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct CycleOptions {
+    pub smoother: Smoother,
+    // smoothing sweeps before and after each coarse grid correction.
+    pub sweeps: usize,
+    pub coarse_solve: CoarseSolve,
+}
+
+impl Default for CycleOptions {
+    fn default() -> CycleOptions {
+        CycleOptions {
+            smoother: Smoother::GaussSeidel,
+            sweeps: 1,
+            coarse_solve: CoarseSolve::Direct,
+        }
+    }
+}
+
+// sparse prolongation from a coarse level, stored by (fine) row.
+pub type Prolongation = Vec<Vec<(usize, f64)>>;
+
+pub struct Level {
+    pub matrix: SparseSymmetricMatrix,
+    pub diagonals: Vec<f64>,
+    // from the next coarser level, empty on the coarsest level.
+    pub prolongation: Prolongation,
+}
+
+// a V-cycle over a hierarchy of levels, finest first. the restriction is
+// the transpose of the prolongation, so the cycle is a symmetric
+// preconditioner whenever the smoother is convergent.
+pub struct Multigrid {
+    pub levels: Vec<Level>,
+    pub options: CycleOptions,
+    coarse_factor: Option<DenseCholesky>,
+}
+
+impl Level {
+    pub fn new(matrix: SparseSymmetricMatrix, prolongation: Prolongation) -> Level {
+        let mut diagonals = vec![0.0; matrix.length + 1];
+        for (i, diagonal) in diagonals.iter_mut().enumerate() {
+            for (j, &index) in matrix.indices[i].iter().enumerate() {
+                if index == i {
+                    *diagonal = matrix.values[i][j];
+                }
+            }
+        }
+        Level {
+            matrix,
+            diagonals,
+            prolongation,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagonals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagonals.is_empty()
+    }
+
+    fn row_product(&self, i: usize, x: &[f64]) -> f64 {
+        self.matrix.indices[i]
+            .iter()
+            .zip(self.matrix.values[i].iter())
+            .fold(0.0, |acc, (&j, value)| acc + value * x[j])
+    }
+
+    fn residual(&self, b: &[f64], x: &[f64]) -> Vec<f64> {
+        (0..self.len()).map(|i| b[i] - self.row_product(i, x)).collect()
+    }
+
+    // a zero diagonal is a null row and column, left untouched.
+    fn smooth(&self, smoother: Smoother, forward: bool, b: &[f64], x: &mut [f64]) {
+        match smoother {
+            Smoother::Jacobi(weight) => {
+                let r = self.residual(b, x);
+                for (i, entry) in x.iter_mut().enumerate() {
+                    if self.diagonals[i] != 0.0 {
+                        *entry += weight * r[i] / self.diagonals[i];
+                    }
+                }
+            }
+            Smoother::GaussSeidel => {
+                let n = self.len();
+                for k in 0..n {
+                    let i = if forward { k } else { n - 1 - k };
+                    if self.diagonals[i] != 0.0 {
+                        let r = b[i] - self.row_product(i, x);
+                        x[i] += r / self.diagonals[i];
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Multigrid {
+    pub fn new(levels: Vec<Level>, options: CycleOptions) -> Multigrid {
+        assert!(!levels.is_empty());
+        let coarse_factor = match options.coarse_solve {
+            CoarseSolve::Direct => {
+                let coarsest = &levels[levels.len() - 1];
+                let n = coarsest.len();
+                let mut dense = vec![vec![0.0; n]; n];
+                for (i, row) in dense.iter_mut().enumerate() {
+                    for (j, &index) in coarsest.matrix.indices[i].iter().enumerate() {
+                        row[index] = coarsest.matrix.values[i][j];
+                    }
+                }
+                Some(DenseCholesky::new(&dense))
+            }
+            CoarseSolve::Smooth(_) => None,
+        };
+        Multigrid {
+            levels,
+            options,
+            coarse_factor,
+        }
+    }
+
+    fn cycle(&self, l: usize, b: &[f64]) -> Vec<f64> {
+        let level = &self.levels[l];
+        let smoother = self.options.smoother;
+        let mut x = vec![0.0; level.len()];
+        if l + 1 == self.levels.len() {
+            match self.coarse_factor {
+                Some(ref factor) => return factor.solve(b),
+                None => {
+                    if let CoarseSolve::Smooth(sweeps) = self.options.coarse_solve {
+                        for _ in 0..sweeps {
+                            level.smooth(smoother, true, b, &mut x);
+                        }
+                        for _ in 0..sweeps {
+                            level.smooth(smoother, false, b, &mut x);
+                        }
+                    }
+                    return x;
+                }
+            }
+        }
+
+        for _ in 0..self.options.sweeps {
+            level.smooth(smoother, true, b, &mut x);
+        }
+
+        let r = level.residual(b, &x);
+        let mut coarse_rhs = vec![0.0; self.levels[l + 1].len()];
+        for (i, row) in level.prolongation.iter().enumerate() {
+            for &(j, weight) in row {
+                coarse_rhs[j] += weight * r[i];
+            }
+        }
+        let correction = self.cycle(l + 1, &coarse_rhs);
+        for (i, row) in level.prolongation.iter().enumerate() {
+            for &(j, weight) in row {
+                x[i] += weight * correction[j];
+            }
+        }
+
+        for _ in 0..self.options.sweeps {
+            level.smooth(smoother, false, b, &mut x);
+        }
+        x
+    }
+}
+
+impl ApproximateInverse for Multigrid {
+    fn apply(&self, v: &Vector) -> Vector {
+        assert_eq!(self.levels[0].len(), v.0.len());
+        Vector(self.cycle(0, &v.0))
+    }
+}
+
+// the galerkin coarse matrix P^T A P for a prolongation onto a coarse level
+// with coarse_length unknowns.
+pub fn galerkin(
+    matrix: &SparseSymmetricMatrix,
+    prolongation: &[Vec<(usize, f64)>],
+    coarse_length: usize,
+) -> SparseSymmetricMatrix {
+    let mut rows: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); coarse_length];
+    for (i, prolongation_row) in prolongation.iter().enumerate() {
+        // row i of A P
+        let mut product: BTreeMap<usize, f64> = BTreeMap::new();
+        for (j, &index) in matrix.indices[i].iter().enumerate() {
+            let value = matrix.values[i][j];
+            for &(c, weight) in &prolongation[index] {
+                *product.entry(c).or_insert(0.0) += value * weight;
+            }
+        }
+        for &(c, weight) in prolongation_row {
+            for (&d, &value) in product.range(c..) {
+                *rows[c].entry(d).or_insert(0.0) += weight * value;
+            }
+        }
+    }
+
+    let mut entries = vec![];
+    for (c, row) in rows.iter().enumerate() {
+        // keep the diagonal even when it is zero, it fixes the length.
+        if !row.contains_key(&c) {
+            entries.push(Entry { x: c, y: c, v: 0.0 });
+        }
+        for (&d, &v) in row {
+            entries.push(Entry { x: c, y: d, v });
+        }
+    }
+    SparseSymmetricMatrix::new(&entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use vector::Vector;
+    use approximate_inverse::ApproximateInverse;
+    use linear_operator::LinearOperator;
+    use test_matrices::laplacian_1d;
+    use multigrid::{Multigrid, Level, CycleOptions, galerkin};
+
+    #[test]
+    fn test_galerkin() {
+        let m = laplacian_1d(4);
+        let prolongation = vec![
+            vec![(0, 1.0)],
+            vec![(0, 1.0)],
+            vec![(1, 1.0)],
+            vec![(1, 1.0)],
+        ];
+        let coarse = galerkin(&m, &prolongation, 2);
+        assert_eq!(coarse.length, 1);
+        assert_eq!(coarse.indices, vec![vec![0, 1], vec![0, 1]]);
+        assert_eq!(coarse.values, vec![vec![2.0, -1.0], vec![-1.0, 2.0]]);
+    }
+
+    #[test]
+    fn test_direct_coarse_solve() {
+        let m = laplacian_1d(5);
+        let mut b = Vector(vec![0.0; 5]);
+        m.apply_into(&Vector(vec![1.0, 2.0, 3.0, 4.0, 5.0]), &mut b);
+        let multigrid = Multigrid::new(vec![Level::new(m, vec![])], CycleOptions::default());
+        let x = multigrid.apply(&b);
+        for (i, value) in x.0.iter().enumerate() {
+            assert!((value - (i + 1) as f64).abs() < 1e-12);
+        }
+    }
+}
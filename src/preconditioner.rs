@@ -1,7 +1,9 @@
 use std::vec::Vec;
+use std::collections::BTreeMap;
 
 use sparse_symmetric_matrix::SparseSymmetricMatrix;
 use vector::Vector;
+use approximate_inverse::ApproximateInverse;
 
 pub struct Preconditioner {
     pub length: usize,
@@ -9,49 +11,177 @@ pub struct Preconditioner {
     pub row_index: Vec<usize>,
     pub column_pointers: Vec<usize>,
     pub inverse_diagonals: Vec<f64>,
+    // multiple of the absolute diagonal that was added before factoring.
+    pub shift: f64,
 }
 
 const MODIFIED_PARAMETER: f64 = 0.97;
 const MIN_DIAGONAL_RATIO: f64 = 0.25;
+const INITIAL_SHIFT: f64 = 1e-3;
+const MAX_SHIFT_ATTEMPTS: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct FactorParams {
+    // fraction of the dropped fill that is added back onto the diagonal:
+    // 0 is plain incomplete cholesky IC(0), 1 is full modified MIC(0).
+    pub modified_parameter: f64,
+    // a pivot smaller than this fraction of the original diagonal is
+    // considered unstable and replaced by the original diagonal.
+    pub min_diagonal_ratio: f64,
+    // level of fill kept by the factorization, IC(k). 0 keeps the sparsity
+    // pattern of the matrix.
+    pub fill_level: usize,
+}
+
+impl Default for FactorParams {
+    fn default() -> FactorParams {
+        FactorParams {
+            modified_parameter: MODIFIED_PARAMETER,
+            min_diagonal_ratio: MIN_DIAGONAL_RATIO,
+            fill_level: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThresholdParams {
+    // entries smaller than this fraction of the norm of their column in the
+    // matrix are dropped.
+    pub drop_tolerance: f64,
+    // at most this many entries beyond those of the matrix are kept in each
+    // column, the largest ones.
+    pub max_fill: usize,
+    pub min_diagonal_ratio: f64,
+}
+
+impl Default for ThresholdParams {
+    fn default() -> ThresholdParams {
+        ThresholdParams {
+            drop_tolerance: 1e-3,
+            max_fill: 10,
+            min_diagonal_ratio: MIN_DIAGONAL_RATIO,
+        }
+    }
+}
 
 impl Preconditioner {
     pub fn new(matrix: &SparseSymmetricMatrix) -> Preconditioner {
-        let mut values = vec![];
-        let mut row_index = vec![];
-        let mut column_pointers = vec![];
-        let mut diagonals = vec![];
-        let mut inverse_diagonals = vec![];
+        Preconditioner::with_params(matrix, &FactorParams::default())
+    }
+
+    pub fn with_params(
+        matrix: &SparseSymmetricMatrix,
+        params: &FactorParams,
+    ) -> Preconditioner {
+        let symbolic = SymbolicFactor::new(matrix, params.fill_level);
+        Preconditioner::from_symbolic(&symbolic, matrix, params)
+    }
+
+    // Manteuffel's shifted factorization: when a pivot breaks down, retry on
+    // A + shift |diag(A)| with a growing shift until every pivot is positive.
+    // the shift that was used is kept in the result. returns None if the
+    // pivots are still not all positive after MAX_SHIFT_ATTEMPTS shifts.
+    pub fn shifted(
+        matrix: &SparseSymmetricMatrix,
+        params: &FactorParams,
+    ) -> Option<Preconditioner> {
+        let symbolic = SymbolicFactor::new(matrix, params.fill_level);
+        let mut factor = Preconditioner::from_pattern(&symbolic);
+        let mut shift = 0.0;
+        for _ in 0..MAX_SHIFT_ATTEMPTS {
+            factor.shift = shift;
+            if factor.numeric(matrix, params) {
+                return Some(factor);
+            }
+            shift = if shift == 0.0 {
+                INITIAL_SHIFT
+            } else {
+                2.0 * shift
+            };
+        }
+        None
+    }
+
+    // numeric factorization on a pattern computed beforehand.
+    pub fn from_symbolic(
+        symbolic: &SymbolicFactor,
+        matrix: &SparseSymmetricMatrix,
+        params: &FactorParams,
+    ) -> Preconditioner {
+        let mut factor = Preconditioner::from_pattern(symbolic);
+        factor.numeric(matrix, params);
+        factor
+    }
+
+    // factor new values with the same sparsity pattern in place, keeping the
+    // pattern (and shift) of this factor. panics if the matrix has entries
+    // outside of the pattern, returns false if a pivot broke down.
+    pub fn refactor(&mut self, matrix: &SparseSymmetricMatrix, params: &FactorParams) -> bool {
+        assert_eq!(self.length, matrix.length + 1);
+        self.numeric(matrix, params)
+    }
+
+    fn from_pattern(symbolic: &SymbolicFactor) -> Preconditioner {
+        Preconditioner {
+            length: symbolic.length,
+            values: vec![0.0; symbolic.row_index.len()],
+            row_index: symbolic.row_index.clone(),
+            column_pointers: symbolic.column_pointers.clone(),
+            inverse_diagonals: vec![0.0; symbolic.length],
+            shift: 0.0,
+        }
+    }
+
+    // factor the matrix on our pattern, which must contain the pattern of the
+    // matrix. returns false if any pivot was not positive, in which case it was
+    // replaced by the original diagonal.
+    fn numeric(&mut self, matrix: &SparseSymmetricMatrix, params: &FactorParams) -> bool {
+        let values = &mut self.values;
+        let row_index = &self.row_index;
+        let column_pointers = &self.column_pointers;
+        let inverse_diagonals = &mut self.inverse_diagonals;
+        let mut diagonals = vec![0.0; matrix.length + 1];
+        let mut positive = true;
 
         // algorithm from Robert Bridson, see:
         // https://www.cs.ubc.ca/~rbridson/fluidsimulation/
 
-        // lower triangle
+        // lower triangle, fill starts out as zero
+        for value in values.iter_mut() {
+            *value = 0.0;
+        }
         for i in 0..matrix.length + 1 {
-            column_pointers.push(row_index.len());
-            diagonals.push(0.0);
-            inverse_diagonals.push(0.0);
+            let mut p = column_pointers[i];
+            let end = column_pointers[i + 1];
             for j in 0..matrix.indices[i].len() {
                 let index = matrix.indices[i][j];
                 let value = matrix.values[i][j];
                 if index > i {
-                    row_index.push(index);
-                    values.push(value);
+                    while p < end && row_index[p] < index {
+                        p += 1;
+                    }
+                    // the pattern has to contain the matrix, otherwise this
+                    // would silently factor a different matrix.
+                    assert!(p < end && row_index[p] == index);
+                    values[p] = value;
                 } else if index == i {
-                    diagonals[i] = value;
-                    inverse_diagonals[i] = value;
+                    diagonals[i] = value + self.shift * value.abs();
                 }
             }
         }
-        column_pointers.push(row_index.len());
+        inverse_diagonals.copy_from_slice(&diagonals);
 
-        let length = column_pointers.len() - 1;
-        for k in 0..length {
+        for k in 0..self.length {
             if diagonals[k] == 0.0 {
                 // null row and column
                 continue;
             }
 
-            let gauss_sidel = inverse_diagonals[k] < (MIN_DIAGONAL_RATIO * diagonals[k]);
+            if inverse_diagonals[k] <= 0.0 || !inverse_diagonals[k].is_finite() {
+                positive = false;
+            }
+
+            let gauss_sidel = inverse_diagonals[k] < (params.min_diagonal_ratio * diagonals[k]);
             if gauss_sidel {
                 inverse_diagonals[k] = 1.0 / diagonals[k].sqrt();
             } else {
@@ -69,30 +199,29 @@ impl Preconditioner {
                 let j = row_index[p];
                 let multiplier = values[p];
                 let mut missing = 0.0;
+                // as in the original search through row j of the matrix,
+                // nothing past its last entry counts as missing.
+                let row_end = matrix.indices[j].last().cloned().unwrap_or(0);
                 let mut a = col_s;
-                let mut b = 0;
-                while a < col_t && row_index[a] < j {
-                    while b < matrix.indices[j].len() {
-                        let current_row = row_index[a];
-                        let index = matrix.indices[j][b];
-                        if index < current_row {
-                            b += 1;
-                        } else if index == current_row {
-                            break;
-                        } else {
-                            missing += values[a];
-                            break;
-                        }
+                while a < col_t && row_index[a] < row_end.min(j) {
+                    let current_row = row_index[a];
+                    let x = column_pointers[current_row];
+                    let y = column_pointers[current_row + 1];
+                    if row_index[x..y].binary_search(&j).is_err() {
+                        missing += values[a];
                     }
                     a += 1;
                 }
+                while a < col_t && row_index[a] < j {
+                    a += 1;
+                }
 
                 if a < col_t && row_index[a] == j {
                     inverse_diagonals[j] -= multiplier * values[a];
                 }
 
                 a += 1;
-                b = column_pointers[j];
+                let mut b = column_pointers[j];
                 while a < col_t && b < column_pointers[j + 1] {
                     let current_row = row_index[a];
                     if row_index[b] < current_row {
@@ -112,15 +241,108 @@ impl Preconditioner {
                     a += 1;
                 }
 
-                inverse_diagonals[j] -= MODIFIED_PARAMETER * multiplier * missing;
+                inverse_diagonals[j] -= params.modified_parameter * multiplier * missing;
+            }
+        }
+        positive
+    }
+
+    // dual threshold incomplete cholesky ICT, computed column by column.
+    // the fill depends on the values rather than on the pattern.
+    pub fn threshold(
+        matrix: &SparseSymmetricMatrix,
+        params: &ThresholdParams,
+    ) -> Preconditioner {
+        let length = matrix.length + 1;
+        let mut columns: Vec<Vec<(usize, f64)>> = vec![];
+        // the finished columns with an entry in a row, and its position.
+        let mut row_links: Vec<Vec<(usize, usize)>> = vec![vec![]; length];
+        let mut inverse_diagonals = vec![0.0; length];
+        let mut work = vec![0.0; length];
+        let mut touched = vec![false; length];
+
+        for k in 0..length {
+            let mut pattern = vec![];
+            let mut diagonal = 0.0;
+            let mut norm = 0.0;
+            for (j, &index) in matrix.indices[k].iter().enumerate() {
+                let value = matrix.values[k][j];
+                if index == k {
+                    diagonal = value;
+                } else if index > k {
+                    work[index] = value;
+                    touched[index] = true;
+                    pattern.push(index);
+                    norm += value * value;
+                }
+            }
+            let original = pattern.len();
+
+            let mut pivot = diagonal;
+            for &(i, position) in &row_links[k] {
+                let column = &columns[i];
+                let multiplier = column[position].1;
+                pivot -= multiplier * multiplier;
+                for &(row, value) in &column[position + 1..] {
+                    if !touched[row] {
+                        touched[row] = true;
+                        pattern.push(row);
+                    }
+                    work[row] -= multiplier * value;
+                }
+            }
+
+            let mut kept = vec![];
+            if diagonal != 0.0 {
+                if pivot < params.min_diagonal_ratio * diagonal {
+                    pivot = diagonal;
+                }
+                inverse_diagonals[k] = 1.0 / pivot.sqrt();
+                let tolerance = params.drop_tolerance * norm.sqrt();
+                kept = pattern
+                    .iter()
+                    .map(|&row| (row, work[row]))
+                    .filter(|&(_, value)| value != 0.0 && value.abs() >= tolerance)
+                    .collect::<Vec<(usize, f64)>>();
+                if kept.len() > original + params.max_fill {
+                    kept.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
+                    kept.truncate(original + params.max_fill);
+                }
+                kept.sort_by_key(|&(row, _)| row);
+                for entry in &mut kept {
+                    entry.1 *= inverse_diagonals[k];
+                }
+            }
+            // otherwise a null row and column
+
+            for &row in &pattern {
+                work[row] = 0.0;
+                touched[row] = false;
+            }
+            for (position, &(row, _)) in kept.iter().enumerate() {
+                row_links[row].push((k, position));
+            }
+            columns.push(kept);
+        }
+
+        let mut values = vec![];
+        let mut row_index = vec![];
+        let mut column_pointers = vec![];
+        for column in &columns {
+            column_pointers.push(row_index.len());
+            for &(row, value) in column {
+                row_index.push(row);
+                values.push(value);
             }
         }
+        column_pointers.push(row_index.len());
         Preconditioner {
             length,
             values,
             row_index,
             column_pointers,
             inverse_diagonals,
+            shift: 0.0,
         }
     }
 
@@ -145,8 +367,7 @@ impl Preconditioner {
 
     fn solve_lower_transpose(&self, v: &Vector) -> Vector {
         let mut result = v.clone();
-        let n = self.length - 1;
-        for i in (0..n).rev() {
+        for i in (0..self.length).rev() {
             let x = self.column_pointers[i];
             let y = self.column_pointers[i + 1];
             for j in x..y {
@@ -159,10 +380,74 @@ impl Preconditioner {
     }
 }
 
+// the symbolic phase of the factorization: the strictly lower triangular
+// pattern, stored by column. it only depends on the sparsity pattern of the
+// matrix, so it can be reused while the values change.
+pub struct SymbolicFactor {
+    pub length: usize,
+    pub row_index: Vec<usize>,
+    pub column_pointers: Vec<usize>,
+}
+
+impl SymbolicFactor {
+    // pattern of IC(fill_level). the original entries have level 0 and a
+    // fill entry created through pivot k has level lev(i, k) + lev(j, k) + 1.
+    pub fn new(matrix: &SparseSymmetricMatrix, fill_level: usize) -> SymbolicFactor {
+        let n = matrix.length + 1;
+        // rows processed so far, with their levels, for every column.
+        let mut columns: Vec<Vec<(usize, usize)>> = vec![vec![]; n];
+        for i in 0..n {
+            let mut row = BTreeMap::new();
+            for &index in matrix.indices[i].iter().filter(|&&index| index < i) {
+                row.insert(index, 0);
+            }
+            let mut next = row.keys().next().cloned();
+            while let Some(k) = next {
+                let level = row[&k];
+                for &(j, other) in &columns[k] {
+                    let fill = level + other + 1;
+                    if fill <= fill_level {
+                        let entry = row.entry(j).or_insert(fill);
+                        if fill < *entry {
+                            *entry = fill;
+                        }
+                    }
+                }
+                next = row.range(k + 1..).next().map(|(&k, _)| k);
+            }
+            for (&j, &level) in &row {
+                columns[j].push((i, level));
+            }
+        }
+
+        let mut row_index = vec![];
+        let mut column_pointers = vec![];
+        for column in &columns {
+            column_pointers.push(row_index.len());
+            row_index.extend(column.iter().map(|&(i, _)| i));
+        }
+        column_pointers.push(row_index.len());
+        SymbolicFactor {
+            length: n,
+            row_index,
+            column_pointers,
+        }
+    }
+}
+
+impl ApproximateInverse for Preconditioner {
+    fn apply(&self, v: &Vector) -> Vector {
+        Preconditioner::apply(self, v)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
-    use preconditioner::Preconditioner;
+    use preconditioner::{Preconditioner, FactorParams, ThresholdParams, SymbolicFactor};
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use test_matrices::{laplacian_2d, converged_iterations};
 
     #[test]
     fn test_positive_definite_matrix_preconditioner() {
@@ -239,4 +524,284 @@ mod tests {
             ]
         );
     }
+
+    // dense reference factorization restricted to the sparsity pattern of m.
+    fn dense_incomplete_cholesky(m: &SparseSymmetricMatrix) -> Vec<Vec<f64>> {
+        let n = m.length + 1;
+        let mut pattern = vec![vec![false; n]; n];
+        let mut l = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for (j, &index) in m.indices[i].iter().enumerate() {
+                pattern[i][index] = true;
+                l[i][index] = m.values[i][j];
+            }
+        }
+        for k in 0..n {
+            l[k][k] = l[k][k].sqrt();
+            for i in k + 1..n {
+                if pattern[i][k] {
+                    l[i][k] /= l[k][k];
+                }
+            }
+            for j in k + 1..n {
+                for i in j..n {
+                    if pattern[i][j] && pattern[i][k] && pattern[j][k] {
+                        l[i][j] -= l[i][k] * l[j][k];
+                    }
+                }
+            }
+        }
+        l
+    }
+
+    #[test]
+    fn test_unmodified_is_incomplete_cholesky() {
+        let m = laplacian_2d(4, 4);
+        let params = FactorParams {
+            modified_parameter: 0.0,
+            min_diagonal_ratio: 0.0,
+            ..FactorParams::default()
+        };
+        let p = Preconditioner::with_params(&m, &params);
+        let l = dense_incomplete_cholesky(&m);
+        for k in 0..p.length {
+            assert!((p.inverse_diagonals[k] - 1.0 / l[k][k]).abs() < 1e-14);
+            for q in p.column_pointers[k]..p.column_pointers[k + 1] {
+                let i = p.row_index[q];
+                assert!((p.values[q] - l[i][k]).abs() < 1e-14);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fully_modified_preserves_row_sums() {
+        let m = laplacian_2d(4, 4);
+        let params = FactorParams {
+            modified_parameter: 1.0,
+            min_diagonal_ratio: 0.0,
+            ..FactorParams::default()
+        };
+        let p = Preconditioner::with_params(&m, &params);
+        let n = p.length;
+        let mut l = vec![vec![0.0; n]; n];
+        for k in 0..n {
+            l[k][k] = 1.0 / p.inverse_diagonals[k];
+            for q in p.column_pointers[k]..p.column_pointers[k + 1] {
+                l[p.row_index[q]][k] = p.values[q];
+            }
+        }
+        for i in 0..n {
+            let factored: f64 = (0..n)
+                .map(|j| (0..n).map(|k| l[i][k] * l[j][k]).sum::<f64>())
+                .sum();
+            let original: f64 = m.values[i].iter().sum();
+            assert!((factored - original).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_fill_level_pattern() {
+        let m = laplacian_2d(3, 3);
+        let column_one = |p: &Preconditioner| {
+            p.row_index[p.column_pointers[1]..p.column_pointers[2]].to_vec()
+        };
+        let level_zero = Preconditioner::new(&m);
+        assert_eq!(level_zero.row_index.len(), 12);
+        assert_eq!(column_one(&level_zero), vec![2, 4]);
+        // eliminating node 0 couples its neighbours 1 and 3.
+        let params = FactorParams {
+            fill_level: 1,
+            ..FactorParams::default()
+        };
+        let level_one = Preconditioner::with_params(&m, &params);
+        assert_eq!(column_one(&level_one), vec![2, 3, 4]);
+        assert!(level_one.row_index.len() > level_zero.row_index.len());
+    }
+
+    // checks that the factor is an exact cholesky factor of m.
+    fn assert_exact_cholesky(m: &SparseSymmetricMatrix, p: &Preconditioner) {
+        let n = m.length + 1;
+        let mut l = vec![vec![0.0; n]; n];
+        for k in 0..n {
+            l[k][k] = 1.0 / p.inverse_diagonals[k];
+            for q in p.column_pointers[k]..p.column_pointers[k + 1] {
+                l[p.row_index[q]][k] = p.values[q];
+            }
+        }
+        let mut a = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for (j, &index) in m.indices[i].iter().enumerate() {
+                a[i][index] = m.values[i][j];
+            }
+        }
+        for i in 0..n {
+            for j in 0..n {
+                let product: f64 = (0..n).map(|k| l[i][k] * l[j][k]).sum();
+                assert!((product - a[i][j]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_fill_is_exact_cholesky() {
+        let m = laplacian_2d(4, 4);
+        let params = FactorParams {
+            fill_level: m.length + 1,
+            ..FactorParams::default()
+        };
+        assert_exact_cholesky(&m, &Preconditioner::with_params(&m, &params));
+    }
+
+    #[test]
+    fn test_fill_reduces_iterations() {
+        let m = laplacian_2d(16, 16);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 256]);
+        let mut iterations = vec![];
+        for level in 0..3 {
+            let params = FactorParams {
+                modified_parameter: 0.0,
+                fill_level: level,
+                ..FactorParams::default()
+            };
+            let p = Preconditioner::with_params(&m, &params);
+            iterations.push(converged_iterations(&srm, &p, &v));
+        }
+        assert!(iterations[1] < iterations[0]);
+        assert!(iterations[2] < iterations[1]);
+    }
+
+    #[test]
+    fn test_threshold_without_dropping_is_exact_cholesky() {
+        let m = laplacian_2d(4, 4);
+        let params = ThresholdParams {
+            drop_tolerance: 0.0,
+            max_fill: m.length + 1,
+            ..ThresholdParams::default()
+        };
+        assert_exact_cholesky(&m, &Preconditioner::threshold(&m, &params));
+    }
+
+    #[test]
+    fn test_threshold_fill_budget() {
+        let m = laplacian_2d(8, 8);
+        let mut previous = 0;
+        for &max_fill in &[0, 1, 2, 4] {
+            let params = ThresholdParams {
+                drop_tolerance: 0.0,
+                max_fill,
+                ..ThresholdParams::default()
+            };
+            let p = Preconditioner::threshold(&m, &params);
+            for k in 0..p.length {
+                let original = m.indices[k].iter().filter(|&&i| i > k).count();
+                let kept = p.column_pointers[k + 1] - p.column_pointers[k];
+                assert!(kept <= original + max_fill);
+            }
+            assert!(p.row_index.len() > previous);
+            previous = p.row_index.len();
+        }
+    }
+
+    #[test]
+    fn test_threshold_drop_tolerance() {
+        let m = laplacian_2d(16, 16);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 256]);
+        let coarse = ThresholdParams {
+            drop_tolerance: 0.1,
+            ..ThresholdParams::default()
+        };
+        let fine = ThresholdParams {
+            drop_tolerance: 1e-3,
+            ..ThresholdParams::default()
+        };
+        let coarse = Preconditioner::threshold(&m, &coarse);
+        let fine = Preconditioner::threshold(&m, &fine);
+        assert!(coarse.row_index.len() < fine.row_index.len());
+        assert!(converged_iterations(&srm, &fine, &v) < converged_iterations(&srm, &coarse, &v));
+    }
+
+    #[test]
+    fn test_shift_not_needed() {
+        let m = laplacian_2d(4, 4);
+        let params = FactorParams::default();
+        let p = Preconditioner::with_params(&m, &params);
+        let shifted = Preconditioner::shifted(&m, &params).unwrap();
+        assert_eq!(shifted.shift, 0.0);
+        assert_eq!(shifted.values, p.values);
+        assert_eq!(shifted.inverse_diagonals, p.inverse_diagonals);
+    }
+
+    #[test]
+    fn test_shift_on_breakdown() {
+        // indefinite, with a negative diagonal entry.
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 0, y: 1, v: 2.0 },
+            Entry { x: 1, y: 1, v: 1.0 },
+            Entry { x: 1, y: 2, v: 1.0 },
+            Entry {
+                x: 2,
+                y: 2,
+                v: -1.0,
+            },
+        ]);
+        let params = FactorParams::default();
+        assert!(
+            Preconditioner::with_params(&m, &params)
+                .inverse_diagonals
+                .iter()
+                .any(|d| d.is_nan())
+        );
+        let p = Preconditioner::shifted(&m, &params).unwrap();
+        assert!(p.shift > 1.0);
+        assert!(p.inverse_diagonals.iter().all(|&d| d > 0.0 && d.is_finite()));
+        assert!(p.values.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_shift_gives_up() {
+        // the diagonal is far too small for any shift of it to help.
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 1e-9 },
+            Entry { x: 0, y: 1, v: 1.0 },
+            Entry { x: 1, y: 1, v: 1e-9 },
+        ]);
+        assert!(Preconditioner::shifted(&m, &FactorParams::default()).is_none());
+    }
+
+    #[test]
+    fn test_refactor() {
+        let m = laplacian_2d(4, 4);
+        let params = FactorParams {
+            fill_level: 1,
+            ..FactorParams::default()
+        };
+        let symbolic = SymbolicFactor::new(&m, params.fill_level);
+        let mut p = Preconditioner::from_symbolic(&symbolic, &m, &params);
+        let fresh = Preconditioner::with_params(&m, &params);
+        assert_eq!(p.values, fresh.values);
+        assert_eq!(p.inverse_diagonals, fresh.inverse_diagonals);
+
+        let mut scaled = laplacian_2d(4, 4);
+        for row in &mut scaled.values {
+            for value in row.iter_mut() {
+                *value *= 3.0;
+            }
+        }
+        assert!(p.refactor(&scaled, &params));
+        let fresh = Preconditioner::with_params(&scaled, &params);
+        assert_eq!(p.row_index, symbolic.row_index);
+        assert_eq!(p.values, fresh.values);
+        assert_eq!(p.inverse_diagonals, fresh.inverse_diagonals);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_refactor_changed_pattern() {
+        let mut p = Preconditioner::new(&laplacian_2d(4, 4));
+        // same size, but coupled to i + 8 instead of i + 4.
+        p.refactor(&laplacian_2d(8, 2), &FactorParams::default());
+    }
 }
@@ -2,75 +2,382 @@ use vector::Vector;
 use sparse_symmetric_matrix::SparseSymmetricMatrix;
 use sparse_row_matrix::SparseRowMatrix;
 use preconditioner::Preconditioner;
+use linear_operator::LinearOperator;
+use approximate_inverse::ApproximateInverse;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Converged,
+    // the right hand side is zero, so there is nothing to solve.
+    ZeroRhs,
+    // the initial preconditioned residual r . M^-1 r is zero, subnormal or
+    // not finite; the preconditioner is unusable for this system.
+    NonNormalRho,
+    MaxIterations,
+    // the curvature s . As of a search direction vanished (or overflowed),
+    // which can only happen when the matrix is not positive definite.
+    Breakdown,
+}
 
 pub struct SolverResult {
     pub completed: bool,
+    pub stop_reason: StopReason,
     pub iterations: usize,
+    // the current iterate, whether or not the solve converged.
     pub best_guess: Vector,
+    pub residual: Vector,
+    pub initial_residual_norm: f64,
+    pub final_residual_norm: f64,
+    // the initial residual norm followed by the norm after every iteration.
+    pub residual_history: Option<Vec<f64>>,
+}
+
+impl SolverResult {
+    pub(crate) fn new(
+        stop_reason: StopReason,
+        iterations: usize,
+        best_guess: Vector,
+        residual: Vector,
+        initial_residual_norm: f64,
+        final_residual_norm: f64,
+        residual_history: Option<Vec<f64>>,
+    ) -> SolverResult {
+        SolverResult {
+            completed: stop_reason == StopReason::Converged,
+            stop_reason,
+            iterations,
+            best_guess,
+            residual,
+            initial_residual_norm,
+            final_residual_norm,
+            residual_history,
+        }
+    }
+
+    // the solution of a system with a zero right hand side is zero.
+    pub(crate) fn zero_rhs(rhs: &Vector, options: &SolverOptions) -> SolverResult {
+        let history = if options.record_history {
+            Some(vec![0.0])
+        } else {
+            None
+        };
+        let zero = Vector(vec![0.0; rhs.0.len()]);
+        SolverResult::new(StopReason::ZeroRhs, 0, zero, rhs.clone(), 0.0, 0.0, history)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResidualNorm {
+    // largest absolute entry of the residual.
+    MaxAbsolute,
+    L2,
+    // sqrt(r . M^-1 r), the energy norm induced by the preconditioner.
+    Preconditioned,
+}
+
+#[derive(Debug, Clone)]
+pub struct SolverOptions {
+    pub max_iterations: usize,
+    pub absolute_tolerance: f64,
+    pub relative_tolerance: f64,
+    pub residual_norm: ResidualNorm,
+    pub record_history: bool,
+    // orthonormal basis of the null space of a singular matrix, which is
+    // projected out of the right hand side and the iterates. only the
+    // conjugate gradient solvers on a single matrix support it. private, as
+    // the projection relies on the orthonormalization in null_space().
+    null_space: Vec<Vector>,
 }
 
 const MAX_ITERATIONS: usize = 100;
 const TOLERANCE_FACTOR: f64 = 1e-5;
+// relative size below which a null space vector counts as dependent.
+const NULL_SPACE_TOLERANCE: f64 = 1e-10;
+
+impl SolverOptions {
+    pub fn new() -> SolverOptions {
+        SolverOptions {
+            max_iterations: MAX_ITERATIONS,
+            absolute_tolerance: 0.0,
+            relative_tolerance: TOLERANCE_FACTOR,
+            residual_norm: ResidualNorm::MaxAbsolute,
+            record_history: false,
+            null_space: vec![],
+        }
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> SolverOptions {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn absolute_tolerance(mut self, tolerance: f64) -> SolverOptions {
+        assert!(tolerance >= 0.0);
+        self.absolute_tolerance = tolerance;
+        self
+    }
+
+    pub fn relative_tolerance(mut self, tolerance: f64) -> SolverOptions {
+        assert!(tolerance >= 0.0);
+        self.relative_tolerance = tolerance;
+        self
+    }
+
+    pub fn residual_norm(mut self, norm: ResidualNorm) -> SolverOptions {
+        self.residual_norm = norm;
+        self
+    }
+
+    pub fn record_history(mut self, record: bool) -> SolverOptions {
+        self.record_history = record;
+        self
+    }
+
+    // the vectors do not need to be orthonormal, that is taken care of here.
+    // vectors that depend on the previous ones are dropped.
+    pub fn null_space(mut self, vectors: Vec<Vector>) -> SolverOptions {
+        let mut basis: Vec<Vector> = vec![];
+        for v in vectors {
+            let length = v.norm();
+            let v = basis.iter().fold(v, |v, b| {
+                let c = b.dot(&v);
+                v + b.scale(-c)
+            });
+            let norm = v.norm();
+            if norm > NULL_SPACE_TOLERANCE * length {
+                basis.push(v.scale(1.0 / norm));
+            }
+        }
+        self.null_space = basis;
+        self
+    }
+
+    pub fn null_space_basis(&self) -> &[Vector] {
+        &self.null_space
+    }
+
+    // remove the null space components from v.
+    pub fn project(&self, v: Vector) -> Vector {
+        self.null_space.iter().fold(v, |v, b| {
+            let c = b.dot(&v);
+            v + b.scale(-c)
+        })
+    }
+
+    // for the solvers that cannot project out a null space.
+    pub(crate) fn assert_no_null_space(&self) {
+        assert!(self.null_space.is_empty());
+    }
+
+    // rho is r . M^-1 r, which the conjugate gradient loop has at hand.
+    pub fn measure(&self, r: &Vector, rho: f64) -> f64 {
+        match self.residual_norm {
+            ResidualNorm::MaxAbsolute => r.largest_absolute_value(),
+            ResidualNorm::L2 => r.norm(),
+            ResidualNorm::Preconditioned => rho.abs().sqrt(),
+        }
+    }
+
+    // the solve has converged once the residual drops below this.
+    pub fn tolerance(&self, initial_residual: f64) -> f64 {
+        self.absolute_tolerance.max(
+            self.relative_tolerance * initial_residual,
+        )
+    }
+}
+
+impl Default for SolverOptions {
+    fn default() -> SolverOptions {
+        SolverOptions::new()
+    }
+}
 
 pub fn solver(m: &SparseSymmetricMatrix, rhs: &Vector) -> SolverResult {
-    let mut r = rhs.clone();
-    let residual_out = r.largest_absolute_value();
-    if residual_out == 0.0 {
-        return SolverResult {
-            completed: false,
-            iterations: 0,
-            best_guess: r,
-        };
+    solve_with(m, rhs, &SolverOptions::default())
+}
+
+pub fn solve_with(
+    m: &SparseSymmetricMatrix,
+    rhs: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    solve_from(m, rhs, &Vector(vec![0.0; rhs.0.len()]), options)
+}
+
+// start the iteration from x0 instead of the zero vector.
+pub fn solve_from(
+    m: &SparseSymmetricMatrix,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    let srm = SparseRowMatrix::new(m);
+    let ic_factor = Preconditioner::new(m);
+    solve_operator(&srm, &ic_factor, rhs, x0, options)
+}
+
+// solve against several right hand sides, building the matrix and the
+// preconditioner once. the results are in the order of the right hand sides.
+pub fn solve_batch(
+    m: &SparseSymmetricMatrix,
+    rhs: &[Vector],
+    options: &SolverOptions,
+) -> Vec<SolverResult> {
+    let srm = SparseRowMatrix::new(m);
+    let ic_factor = Preconditioner::new(m);
+    let x0 = Vector(vec![0.0; m.length + 1]);
+    rhs.iter()
+        .map(|b| solve_operator(&srm, &ic_factor, b, &x0, options))
+        .collect()
+}
+
+// the operator only needs to provide products, so it does not have to be
+// assembled, and any preconditioner can be plugged in.
+pub fn solve_operator<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+) -> SolverResult {
+    conjugate_gradient(a, preconditioner, rhs, x0, options, None)
+}
+
+// the conjugate gradient loop proper. the correction, if any, is subtracted
+// from every preconditioned residual before it enters the search direction,
+// so that the directions can be kept clear of a subspace.
+pub(crate) fn conjugate_gradient<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+    x0: &Vector,
+    options: &SolverOptions,
+    correction: Option<&dyn Fn(&Vector) -> Vector>,
+) -> SolverResult {
+    let direction = |z: Vector| match correction {
+        Some(correction) => correction(&z).scale(-1.0) + z,
+        None => z,
+    };
+    let n = a.dimension();
+    assert_eq!(n, rhs.0.len());
+    assert_eq!(n, x0.0.len());
+    // for a singular matrix only the part of the right hand side in the
+    // range can be matched. keeping the iterates orthogonal to the null
+    // space gives the minimum norm solution.
+    let rhs = &options.project(rhs.clone());
+    if rhs.largest_absolute_value() == 0.0 {
+        return SolverResult::zero_rhs(rhs, options);
     }
 
-    let ic_factor = Preconditioner::new(&m);
-    let z = ic_factor.apply(&r);
+    // tolerances are relative to the right hand side, so that a good initial
+    // guess does not tighten the target.
+    let rhs_norm = match options.residual_norm {
+        ResidualNorm::Preconditioned => {
+            options.measure(rhs, preconditioner.apply(rhs).dot(rhs))
+        }
+        _ => options.measure(rhs, 0.0),
+    };
+    let tolerance = options.tolerance(rhs_norm);
 
+    let mut result = options.project(x0.clone());
+    let mut q = Vector(vec![0.0; n]);
+    a.apply_into(&result, &mut q);
+    let mut r = rhs.clone() + q.scale(-1.0);
+    let z = options.project(preconditioner.apply(&r));
     let mut rho = z.dot(&r);
+    let initial_residual = options.measure(&r, rho);
+    let mut history = if options.record_history {
+        Some(vec![initial_residual])
+    } else {
+        None
+    };
+    if initial_residual <= tolerance {
+        return SolverResult::new(
+            StopReason::Converged,
+            0,
+            result,
+            r,
+            initial_residual,
+            initial_residual,
+            history,
+        );
+    }
     if !rho.is_normal() {
-        return SolverResult {
-            completed: false,
-            iterations: 0,
-            best_guess: r,
-        };
+        return SolverResult::new(
+            StopReason::NonNormalRho,
+            0,
+            result,
+            r,
+            initial_residual,
+            initial_residual,
+            history,
+        );
     }
 
-    let tolerance = TOLERANCE_FACTOR * residual_out;
-    let mut result = Vector(vec![0.0; rhs.0.len()]);
-    let mut s = z;
-    let srm = SparseRowMatrix::new(&m);
-
-    for iteration in 0..MAX_ITERATIONS {
-        let mut z = srm.apply(&s);
-        let alpha = rho / s.dot(&z);
-        result = result + s.scale(alpha);
-        r = r + z.scale(-alpha);
-        if r.largest_absolute_value() < tolerance {
-            return SolverResult {
-                completed: true,
-                iterations: iteration + 1,
-                best_guess: result,
-            };
-        }
-        z = ic_factor.apply(&r);
+    let mut residual = initial_residual;
+    let mut s = direction(z);
+
+    for iteration in 0..options.max_iterations {
+        a.apply_into(&s, &mut q);
+        let curvature = s.dot(&q);
+        if !curvature.is_normal() {
+            return SolverResult::new(
+                StopReason::Breakdown,
+                iteration,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
+        let alpha = rho / curvature;
+        result = options.project(result + s.scale(alpha));
+        r = r + q.scale(-alpha);
+        let z = options.project(preconditioner.apply(&r));
         let rho_new = z.dot(&r);
+        residual = options.measure(&r, rho_new);
+        if let Some(ref mut history) = history {
+            history.push(residual);
+        }
+        if residual <= tolerance {
+            return SolverResult::new(
+                StopReason::Converged,
+                iteration + 1,
+                result,
+                r,
+                initial_residual,
+                residual,
+                history,
+            );
+        }
         let beta = rho_new / rho;
-        s = z + s.scale(beta);
+        s = direction(z) + s.scale(beta);
         rho = rho_new;
     }
 
-    SolverResult {
-        completed: false,
-        iterations: MAX_ITERATIONS,
-        best_guess: r,
-    }
+    SolverResult::new(
+        StopReason::MaxIterations,
+        options.max_iterations,
+        result,
+        r,
+        initial_residual,
+        residual,
+        history,
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use preconditioner::Preconditioner;
+    use linear_operator::LinearOperator;
+    use approximate_inverse::{ApproximateInverse, Identity};
     use vector::Vector;
-    use solver::solver;
+    use test_matrices::{laplacian_2d, neumann};
+    use solver::{solver, solve_with, solve_from, solve_batch, solve_operator, SolverOptions,
+                 ResidualNorm, StopReason};
 
     #[test]
     fn test_solve() {
@@ -83,10 +390,10 @@ mod tests {
         let v = Vector(vec![5.0, 6.0, 7.0]);
         let result = solver(&m, &v);
         assert_eq!(result.completed, true);
-        assert_eq!(result.iterations, 3);
-        assert_eq!(result.best_guess.0[0], 1.1666666673535904);
-        assert_eq!(result.best_guess.0[1], 0.08333333333467363);
-        assert_eq!(result.best_guess.0[2], 0.5694444443288402);
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.best_guess.0[0], 1.1666674087694608);
+        assert_eq!(result.best_guess.0[1], 0.0833110800778692);
+        assert_eq!(result.best_guess.0[2], 0.5694629884317245);
     }
 
     #[test]
@@ -109,8 +416,340 @@ mod tests {
         assert_eq!(result.completed, true);
         assert_eq!(result.iterations, 3);
         assert_eq!(result.best_guess.0[0], 0.5882352941176471);
-        assert_eq!(result.best_guess.0[1], -0.764705882352941);
-        assert_eq!(result.best_guess.0[2], 0.17647058823529407);
-        assert_eq!(result.best_guess.0[3], 1.0980392156862744);
+        assert_eq!(result.best_guess.0[1], -0.7647058823529416);
+        assert_eq!(result.best_guess.0[2], 0.17647058823529416);
+        assert_eq!(result.best_guess.0[3], 1.0980392156862748);
+    }
+
+    #[test]
+    fn test_solve_max_iterations() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 3.0 },
+            Entry { x: 1, y: 0, v: 1.0 },
+            Entry { x: 1, y: 1, v: 4.0 },
+            Entry { x: 2, y: 1, v: 1.0 },
+            Entry { x: 3, y: 1, v: 3.0 },
+            Entry {
+                x: 2,
+                y: 2,
+                v: 10.0,
+            },
+            Entry { x: 3, y: 3, v: 3.0 },
+        ]);
+        let v = Vector(vec![1.0, 1.0, 1.0, 1.0]);
+        let options = SolverOptions::new().max_iterations(1);
+        let result = solve_with(&m, &v, &options);
+        assert_eq!(result.completed, false);
+        assert_eq!(result.stop_reason, StopReason::MaxIterations);
+        assert_eq!(result.iterations, 1);
+    }
+
+    #[test]
+    fn test_solve_residual_norms() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 4.0 },
+            Entry { x: 1, y: 0, v: -1.0 },
+            Entry { x: 1, y: 1, v: 4.0 },
+            Entry { x: 2, y: 1, v: -1.0 },
+            Entry { x: 2, y: 2, v: 4.0 },
+            Entry { x: 3, y: 2, v: -1.0 },
+            Entry { x: 3, y: 3, v: 4.0 },
+        ]);
+        let v = Vector(vec![1.0, 2.0, 3.0, 4.0]);
+        for &norm in &[
+            ResidualNorm::MaxAbsolute,
+            ResidualNorm::L2,
+            ResidualNorm::Preconditioned,
+        ]
+        {
+            let options = SolverOptions::new()
+                .relative_tolerance(0.0)
+                .absolute_tolerance(1e-12)
+                .residual_norm(norm);
+            let result = solve_with(&m, &v, &options);
+            assert_eq!(result.completed, true);
+            let expected = [
+                0.4880382775119617,
+                0.9521531100478469,
+                1.3205741626794258,
+                1.3301435406698565,
+            ];
+            for (x, y) in result.best_guess.0.iter().zip(expected.iter()) {
+                assert!((x - y).abs() < 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_history() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 0, y: 1, v: 5.0 },
+            Entry { x: 0, y: 2, v: 6.0 },
+            Entry { x: 1, y: 1, v: 2.0 },
+        ]);
+        let v = Vector(vec![5.0, 6.0, 7.0]);
+        let result = solve_with(&m, &v, &SolverOptions::new().record_history(true));
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert_eq!(result.initial_residual_norm, 7.0);
+        let history = result.residual_history.unwrap();
+        assert_eq!(history.len(), result.iterations + 1);
+        assert_eq!(history[0], result.initial_residual_norm);
+        assert_eq!(history[result.iterations], result.final_residual_norm);
+        assert!(result.final_residual_norm <= 1e-5 * 7.0);
+
+        let result = solver(&m, &v);
+        assert!(result.residual_history.is_none());
+    }
+
+    fn residual_of(m: &SparseSymmetricMatrix, rhs: &Vector, x: &Vector) -> Vector {
+        rhs.clone() + SparseRowMatrix::new(m).apply(x).scale(-1.0)
+    }
+
+    #[test]
+    fn test_exit_zero_rhs() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 2.0 },
+            Entry { x: 1, y: 1, v: 2.0 },
+        ]);
+        let v = Vector(vec![0.0, 0.0]);
+        let result = solver(&m, &v);
+        assert_eq!(result.stop_reason, StopReason::ZeroRhs);
+        assert_eq!(result.completed, false);
+        assert_eq!(result.iterations, 0);
+        assert_eq!(result.best_guess.0, vec![0.0, 0.0]);
+        assert_eq!(result.residual.0, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_exit_non_normal_rho() {
+        // null diagonal, so the preconditioner maps everything to zero.
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 0.0 },
+            Entry { x: 0, y: 1, v: 1.0 },
+            Entry { x: 1, y: 1, v: 0.0 },
+        ]);
+        let v = Vector(vec![1.0, 1.0]);
+        let result = solver(&m, &v);
+        assert_eq!(result.stop_reason, StopReason::NonNormalRho);
+        assert_eq!(result.completed, false);
+        assert_eq!(result.iterations, 0);
+        assert_eq!(result.best_guess.0, vec![0.0, 0.0]);
+        assert_eq!(result.residual.0, vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_exit_breakdown() {
+        // indefinite; the first search direction (1, -2) has zero curvature.
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry {
+                x: 0,
+                y: 1,
+                v: 1.25,
+            },
+            Entry { x: 1, y: 1, v: 1.0 },
+        ]);
+        let v = Vector(vec![-1.5, -3.875]);
+        let result = solver(&m, &v);
+        assert_eq!(result.stop_reason, StopReason::Breakdown);
+        assert_eq!(result.completed, false);
+        assert_eq!(result.iterations, 0);
+        assert_eq!(result.best_guess.0, vec![0.0, 0.0]);
+        assert_eq!(result.residual.0, v.0);
+    }
+
+    #[test]
+    fn test_exit_max_iterations() {
+        // the incomplete factor of a tridiagonal matrix would be exact.
+        let m = laplacian_2d(4, 4);
+        let v = Vector((0..16).map(|i| i as f64).collect());
+        let options = SolverOptions::new().max_iterations(1);
+        let result = solve_with(&m, &v, &options);
+        assert_eq!(result.stop_reason, StopReason::MaxIterations);
+        assert_eq!(result.completed, false);
+        let expected = residual_of(&m, &v, &result.best_guess);
+        for (x, y) in result.residual.0.iter().zip(expected.0.iter()) {
+            assert!((x - y).abs() < 1e-12);
+        }
+        assert!(result.best_guess.largest_absolute_value() > 0.0);
+    }
+
+    #[test]
+    fn test_exit_converged() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 3.0 },
+            Entry { x: 1, y: 0, v: 1.0 },
+            Entry { x: 1, y: 1, v: 4.0 },
+            Entry { x: 2, y: 1, v: 1.0 },
+            Entry { x: 3, y: 1, v: 3.0 },
+            Entry {
+                x: 2,
+                y: 2,
+                v: 10.0,
+            },
+            Entry { x: 3, y: 3, v: 3.0 },
+        ]);
+        let v = Vector(vec![1.0, 1.0, 1.0, 1.0]);
+        let result = solver(&m, &v);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        let expected = residual_of(&m, &v, &result.best_guess);
+        for (x, y) in result.residual.0.iter().zip(expected.0.iter()) {
+            assert!((x - y).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_warm_start() {
+        // the incomplete factor of a tridiagonal matrix would be exact.
+        let m = laplacian_2d(4, 4);
+        let options = SolverOptions::new().relative_tolerance(1e-10);
+        let v = Vector((0..16).map(|i| i as f64).collect());
+        let cold = solve_with(&m, &v, &options);
+        assert_eq!(cold.stop_reason, StopReason::Converged);
+
+        let exact = solve_from(&m, &v, &cold.best_guess, &options);
+        assert_eq!(exact.stop_reason, StopReason::Converged);
+        assert_eq!(exact.iterations, 0);
+        assert_eq!(exact.best_guess.0, cold.best_guess.0);
+
+        let mut perturbed = v.clone();
+        perturbed.0[15] += 0.001;
+        let warm = solve_from(&m, &perturbed, &cold.best_guess, &options);
+        let restart = solve_with(&m, &perturbed, &options);
+        assert_eq!(warm.stop_reason, StopReason::Converged);
+        assert!(warm.iterations < restart.iterations);
+        for (x, y) in warm.best_guess.0.iter().zip(restart.best_guess.0.iter()) {
+            assert!((x - y).abs() < 1e-8);
+        }
+    }
+
+    // the 1d laplacian stencil [-1 2 -1], never assembled.
+    struct Stencil(usize);
+
+    impl LinearOperator for Stencil {
+        fn dimension(&self) -> usize {
+            self.0
+        }
+
+        fn apply_into(&self, v: &Vector, result: &mut Vector) {
+            for i in 0..self.0 {
+                let left = if i > 0 { v.0[i - 1] } else { 0.0 };
+                let right = if i + 1 < self.0 { v.0[i + 1] } else { 0.0 };
+                result.0[i] = 2.0 * v.0[i] - left - right;
+            }
+        }
+    }
+
+    #[test]
+    fn test_matrix_free() {
+        let n = 16;
+        let mut entries = vec![];
+        for i in 0..n {
+            entries.push(Entry { x: i, y: i, v: 2.0 });
+            if i + 1 < n {
+                entries.push(Entry {
+                    x: i,
+                    y: i + 1,
+                    v: -1.0,
+                });
+            }
+        }
+        let m = SparseSymmetricMatrix::new(&entries);
+        let v = Vector((0..n).map(|i| i as f64).collect());
+        let x0 = Vector(vec![0.0; n]);
+        let options = SolverOptions::new();
+        let assembled = solve_with(&m, &v, &options);
+        let result = solve_operator(&Stencil(n), &Preconditioner::new(&m), &v, &x0, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        assert_eq!(result.iterations, assembled.iterations);
+        assert_eq!(result.best_guess.0, assembled.best_guess.0);
+    }
+
+    // scales by the inverse of the stencil diagonal.
+    struct HalfScale;
+
+    impl ApproximateInverse for HalfScale {
+        fn apply(&self, v: &Vector) -> Vector {
+            v.scale(0.5)
+        }
+    }
+
+    #[test]
+    fn test_pluggable_preconditioners() {
+        let n = 16;
+        let v = Vector((0..n).map(|i| i as f64).collect());
+        let x0 = Vector(vec![0.0; n]);
+        let options = SolverOptions::new().relative_tolerance(1e-10);
+        let plain = solve_operator(&Stencil(n), &Identity, &v, &x0, &options);
+        let scaled = solve_operator(&Stencil(n), &HalfScale, &v, &x0, &options);
+        assert_eq!(plain.stop_reason, StopReason::Converged);
+        assert_eq!(scaled.stop_reason, StopReason::Converged);
+        // plain cg is exact after n steps on an n dimensional system.
+        assert!(plain.iterations <= n);
+        assert_eq!(plain.iterations, scaled.iterations);
+        for (x, y) in plain.best_guess.0.iter().zip(scaled.best_guess.0.iter()) {
+            assert!((x - y).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_null_space_basis() {
+        let options = SolverOptions::new().null_space(vec![
+            Vector(vec![2.0, 0.0, 0.0]),
+            Vector(vec![1.0, 1.0, 0.0]),
+            Vector(vec![3.0, 3.0, 0.0]),
+        ]);
+        let basis = options.null_space_basis();
+        assert_eq!(basis.len(), 2);
+        assert_eq!(basis[0].0, vec![1.0, 0.0, 0.0]);
+        assert_eq!(basis[1].0, vec![0.0, 1.0, 0.0]);
+        let projected = options.project(Vector(vec![4.0, 5.0, 6.0]));
+        assert_eq!(projected.0, vec![0.0, 0.0, 6.0]);
+    }
+
+    #[test]
+    fn test_singular_system() {
+        let n = 12;
+        let m = neumann(n);
+        // not in the range of the matrix, its mean has to be removed.
+        let v = Vector((0..n * n).map(|i| (i % 7) as f64).collect());
+        let options = SolverOptions::new()
+            .relative_tolerance(1e-8)
+            .null_space(vec![Vector(vec![1.0; n * n])]);
+        let result = solve_with(&m, &v, &options);
+        assert_eq!(result.stop_reason, StopReason::Converged);
+        // the minimum norm solution has zero mean.
+        let mean = result.best_guess.0.iter().sum::<f64>() / (n * n) as f64;
+        assert!(mean.abs() < 1e-12);
+        let projected = options.project(v.clone());
+        let product = SparseRowMatrix::new(&m).apply(&result.best_guess);
+        for (a, b) in projected.0.iter().zip(product.0.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+
+        // a right hand side in the null space has the zero solution.
+        let constant = solve_with(&m, &Vector(vec![3.0; n * n]), &options);
+        assert_eq!(constant.stop_reason, StopReason::ZeroRhs);
+    }
+
+    #[test]
+    fn test_batch() {
+        let m = laplacian_2d(8, 8);
+        let rhs = vec![
+            Vector(vec![1.0; 64]),
+            Vector(vec![0.0; 64]),
+            Vector((0..64).map(|i| i as f64).collect()),
+        ];
+        let options = SolverOptions::new().relative_tolerance(1e-8);
+        let results = solve_batch(&m, &rhs, &options);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].stop_reason, StopReason::ZeroRhs);
+        for (b, result) in rhs.iter().zip(results.iter()) {
+            let single = solve_with(&m, b, &options);
+            assert_eq!(result.stop_reason, single.stop_reason);
+            assert_eq!(result.iterations, single.iterations);
+            assert_eq!(result.best_guess.0, single.best_guess.0);
+        }
     }
 }
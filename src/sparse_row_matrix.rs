@@ -2,15 +2,16 @@ use std::vec::Vec;
 use std::fmt;
 
 use vector::Vector;
-use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
 use validity::Validity;
+use linear_operator::LinearOperator;
 
 // we use this structure only for multiplication as it is more
 // efficient for this purpose than SparseSymmetricMatrix.
 pub struct SparseRowMatrix {
-    values: Vec<f64>,
-    column_index: Vec<usize>,
-    row_pointers: Vec<usize>,
+    pub(crate) values: Vec<f64>,
+    pub(crate) column_index: Vec<usize>,
+    pub(crate) row_pointers: Vec<usize>,
 }
 
 impl SparseRowMatrix {
@@ -34,26 +35,104 @@ impl SparseRowMatrix {
         }
     }
 
+    // general, not necessarily symmetric, matrix with entry x, y in row x and
+    // column y. as for the symmetric matrix, the first of duplicate entries
+    // is kept.
+    pub fn from_entries(entries: &[Entry]) -> SparseRowMatrix {
+        let mut sorted_entries = entries.to_vec();
+        sorted_entries.sort_by(|a, b| if a.x == b.x {
+            a.y.cmp(&b.y)
+        } else {
+            a.x.cmp(&b.x)
+        });
+        sorted_entries.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+        let length = sorted_entries.iter().fold(
+            0,
+            |acc, e| acc.max(e.x + 1).max(e.y + 1),
+        );
+        let mut rows = vec![vec![]; length];
+        for entry in sorted_entries {
+            rows[entry.x].push((entry.y, entry.v));
+        }
+        SparseRowMatrix::from_rows(&rows)
+    }
+
+    // general, not necessarily symmetric, matrix from (column, value) rows.
+    pub fn from_rows(rows: &[Vec<(usize, f64)>]) -> SparseRowMatrix {
+        let mut values = vec![];
+        let mut column_index = vec![];
+        let mut row_pointers = vec![0];
+        for row in rows {
+            for &(j, v) in row {
+                values.push(v);
+                column_index.push(j);
+            }
+            row_pointers.push(values.len());
+        }
+
+        SparseRowMatrix {
+            values,
+            column_index,
+            row_pointers,
+        }
+    }
+
+    pub fn transpose(&self) -> SparseRowMatrix {
+        let n = self.len();
+        let mut rows = vec![vec![]; n];
+        for i in 0..n {
+            for j in self.row_pointers[i]..self.row_pointers[i + 1] {
+                rows[self.column_index[j]].push((i, self.values[j]));
+            }
+        }
+        SparseRowMatrix::from_rows(&rows)
+    }
+
+    // refresh the values from a matrix with the same sparsity pattern,
+    // without reallocating.
+    pub fn update_values(&mut self, matrix: &SparseSymmetricMatrix) {
+        assert_eq!(self.len(), matrix.length + 1);
+        for i in 0..matrix.length + 1 {
+            let x = self.row_pointers[i];
+            assert_eq!(self.row_pointers[i + 1] - x, matrix.indices[i].len());
+            for (j, &item) in matrix.indices[i].iter().enumerate() {
+                assert_eq!(self.column_index[x + j], item);
+                self.values[x + j] = matrix.values[i][j];
+            }
+        }
+    }
+
     fn len(&self) -> usize {
         self.row_pointers.len() - 1
     }
 
     // do not use the Mul trait, we want to borrow self.
     pub fn apply(&self, rhs: &Vector) -> Vector {
-        assert_eq!(self.len(), rhs.0.len());
+        let mut result = Vector(vec![0.0; self.len()]);
+        self.apply_into(rhs, &mut result);
+        result
+    }
+}
+
+impl LinearOperator for SparseRowMatrix {
+    fn dimension(&self) -> usize {
+        self.len()
+    }
+
+    fn apply_into(&self, v: &Vector, result: &mut Vector) {
+        assert_eq!(self.len(), v.0.len());
+        assert_eq!(self.len(), result.0.len());
         assert!(self.is_valid());
-        let n = self.len();
-        let mut result = vec![0.0; n];
-        #[cfg_attr(feature = "cargo-clippy", allow(needless_range_loop))]
-        for i in 0..n {
+        for (i, entry) in result.0.iter_mut().enumerate() {
             let x = self.row_pointers[i];
             let y = self.row_pointers[i + 1];
+            let mut sum = 0.0;
             for j in x..y {
                 let index = self.column_index[j];
-                result[i] += self.values[j] * rhs.0[index];
+                sum += self.values[j] * v.0[index];
             }
+            *entry = sum;
         }
-        Vector(result)
     }
 }
 
@@ -122,4 +201,55 @@ mod tests {
         let result = srm.apply(&v);
         assert_eq!(result.0, vec![29.0, 9.0, 6.0]);
     }
+
+    #[test]
+    fn test_from_entries() {
+        let srm = SparseRowMatrix::from_entries(&vec![
+            Entry { x: 2, y: 0, v: 4.0 },
+            Entry { x: 0, y: 2, v: 2.0 },
+            Entry { x: 1, y: 1, v: 3.0 },
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 0, y: 2, v: 5.0 },
+        ]);
+        assert_eq!(srm.values, vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(srm.column_index, vec![0, 2, 1, 0]);
+        assert_eq!(srm.row_pointers, vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let srm = SparseRowMatrix::from_rows(&vec![
+            vec![(0, 1.0), (2, 2.0)],
+            vec![(1, 3.0)],
+            vec![(0, 4.0)],
+        ]);
+        let transpose = srm.transpose();
+        assert_eq!(transpose.values, vec![1.0, 4.0, 3.0, 2.0]);
+        assert_eq!(transpose.column_index, vec![0, 2, 1, 0]);
+        assert_eq!(transpose.row_pointers, vec![0, 2, 3, 4]);
+        let v = Vector(vec![1.0, 2.0, 3.0]);
+        assert_eq!(srm.apply(&v).0, vec![7.0, 6.0, 4.0]);
+        assert_eq!(transpose.apply(&v).0, vec![13.0, 6.0, 2.0]);
+    }
+
+    #[test]
+    fn test_update_values() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 1.0 },
+            Entry { x: 0, y: 1, v: 5.0 },
+            Entry { x: 0, y: 2, v: 6.0 },
+            Entry { x: 1, y: 1, v: 2.0 },
+        ]);
+        let mut srm = SparseRowMatrix::new(&m);
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 2.0 },
+            Entry { x: 0, y: 1, v: 3.0 },
+            Entry { x: 0, y: 2, v: 4.0 },
+            Entry { x: 1, y: 1, v: 5.0 },
+        ]);
+        srm.update_values(&m);
+        assert_eq!(srm.values, vec![2.0, 3.0, 4.0, 3.0, 5.0, 4.0]);
+        assert_eq!(srm.column_index, vec![0, 1, 2, 0, 1, 0]);
+        assert_eq!(srm.row_pointers, vec![0, 3, 5, 6]);
+    }
 }
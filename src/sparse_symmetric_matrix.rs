@@ -3,6 +3,8 @@ use std::cmp::{min, max};
 use std::fmt;
 
 use validity::Validity;
+use vector::Vector;
+use linear_operator::LinearOperator;
 
 pub struct SparseSymmetricMatrix {
     pub length: usize,
@@ -72,6 +74,23 @@ impl SparseSymmetricMatrix {
     }
 }
 
+impl LinearOperator for SparseSymmetricMatrix {
+    fn dimension(&self) -> usize {
+        self.length + 1
+    }
+
+    fn apply_into(&self, v: &Vector, result: &mut Vector) {
+        assert_eq!(self.dimension(), v.0.len());
+        assert_eq!(self.dimension(), result.0.len());
+        for (i, entry) in result.0.iter_mut().enumerate() {
+            *entry = self.indices[i].iter().zip(self.values[i].iter()).fold(
+                0.0,
+                |acc, (&j, value)| acc + value * v.0[j],
+            );
+        }
+    }
+}
+
 impl Validity for SparseSymmetricMatrix {
     fn is_valid(&self) -> bool {
         self.values
@@ -178,6 +197,20 @@ fn test_duplicate_construct() {
     );
 }
 
+#[test]
+fn test_apply_into() {
+    let m = SparseSymmetricMatrix::new(&vec![
+        Entry { x: 0, y: 0, v: 1.0 },
+        Entry { x: 0, y: 1, v: 5.0 },
+        Entry { x: 0, y: 2, v: 6.0 },
+        Entry { x: 1, y: 1, v: 2.0 },
+    ]);
+    let mut result = Vector(vec![1.0; 3]);
+    assert_eq!(m.dimension(), 3);
+    m.apply_into(&Vector(vec![1.0, 2.0, 3.0]), &mut result);
+    assert_eq!(result.0, vec![29.0, 9.0, 6.0]);
+}
+
 #[test]
 fn test_sparse_construct() {
     let m = SparseSymmetricMatrix::new(&vec![
@@ -0,0 +1,106 @@
+use std::vec::Vec;
+
+use sparse_symmetric_matrix::SparseSymmetricMatrix;
+use sparse_row_matrix::SparseRowMatrix;
+use vector::Vector;
+use approximate_inverse::ApproximateInverse;
+
+// symmetric successive over-relaxation, a forward and a backward
+// gauss-seidel sweep relaxed by omega. omega = 1 is symmetric gauss-seidel.
+pub struct Ssor {
+    pub omega: f64,
+    pub matrix: SparseRowMatrix,
+    pub diagonals: Vec<f64>,
+}
+
+impl Ssor {
+    pub fn new(matrix: &SparseSymmetricMatrix, omega: f64) -> Ssor {
+        assert!(omega > 0.0 && omega < 2.0);
+        let mut diagonals = vec![0.0; matrix.length + 1];
+        for (i, diagonal) in diagonals.iter_mut().enumerate() {
+            for (j, &index) in matrix.indices[i].iter().enumerate() {
+                if index == i {
+                    *diagonal = matrix.values[i][j];
+                }
+            }
+        }
+        Ssor {
+            omega,
+            matrix: SparseRowMatrix::new(matrix),
+            diagonals,
+        }
+    }
+}
+
+impl ApproximateInverse for Ssor {
+    // M^-1 = omega (2 - omega) (D + omega U)^-1 D (D + omega L)^-1
+    fn apply(&self, v: &Vector) -> Vector {
+        let n = self.diagonals.len();
+        assert_eq!(n, v.0.len());
+        let m = &self.matrix;
+        let mut result = v.clone();
+
+        for i in 0..n {
+            if self.diagonals[i] == 0.0 {
+                // null row and column
+                result.0[i] = 0.0;
+                continue;
+            }
+            let mut sum = result.0[i];
+            for p in m.row_pointers[i]..m.row_pointers[i + 1] {
+                let j = m.column_index[p];
+                if j < i {
+                    sum -= self.omega * m.values[p] * result.0[j];
+                }
+            }
+            result.0[i] = sum / self.diagonals[i];
+        }
+
+        for i in (0..n).rev() {
+            if self.diagonals[i] == 0.0 {
+                continue;
+            }
+            let mut sum = self.diagonals[i] * result.0[i];
+            for p in m.row_pointers[i]..m.row_pointers[i + 1] {
+                let j = m.column_index[p];
+                if j > i {
+                    sum -= self.omega * m.values[p] * result.0[j];
+                }
+            }
+            result.0[i] = sum / self.diagonals[i];
+        }
+
+        result.scale(self.omega * (2.0 - self.omega))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+    use sparse_row_matrix::SparseRowMatrix;
+    use vector::Vector;
+    use approximate_inverse::{ApproximateInverse, Identity};
+    use test_matrices::{laplacian_2d, converged_iterations};
+    use ssor::Ssor;
+
+    #[test]
+    fn test_symmetric_gauss_seidel() {
+        let m = SparseSymmetricMatrix::new(&vec![
+            Entry { x: 0, y: 0, v: 4.0 },
+            Entry { x: 0, y: 1, v: 1.0 },
+            Entry { x: 1, y: 1, v: 3.0 },
+        ]);
+        let result = Ssor::new(&m, 1.0).apply(&Vector(vec![1.0, 2.0]));
+        assert!((result.0[0] - (1.0 - 1.75 / 3.0) / 4.0).abs() < 1e-15);
+        assert!((result.0[1] - 1.75 / 3.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_fewer_iterations_than_plain_cg() {
+        let m = laplacian_2d(16, 16);
+        let srm = SparseRowMatrix::new(&m);
+        let v = Vector(vec![1.0; 256]);
+        let plain = converged_iterations(&srm, &Identity, &v);
+        assert!(converged_iterations(&srm, &Ssor::new(&m, 1.5), &v) < plain);
+    }
+}
@@ -0,0 +1,138 @@
+use sparse_symmetric_matrix::{SparseSymmetricMatrix, Entry};
+use sparse_row_matrix::SparseRowMatrix;
+use linear_operator::LinearOperator;
+use approximate_inverse::ApproximateInverse;
+use vector::Vector;
+use solver::{solve_operator, SolverOptions, StopReason};
+
+// 3-point laplacian [-1 2 -1] with dirichlet boundaries.
+pub fn laplacian_1d(n: usize) -> SparseSymmetricMatrix {
+    let mut entries = vec![];
+    for i in 0..n {
+        entries.push(Entry { x: i, y: i, v: 2.0 });
+        if i + 1 < n {
+            entries.push(Entry {
+                x: i,
+                y: i + 1,
+                v: -1.0,
+            });
+        }
+    }
+    SparseSymmetricMatrix::new(&entries)
+}
+
+// 5-point laplacian on an nx by ny grid with dirichlet boundaries.
+pub fn laplacian_2d(nx: usize, ny: usize) -> SparseSymmetricMatrix {
+    let mut entries = vec![];
+    for y in 0..ny {
+        for x in 0..nx {
+            let i = y * nx + x;
+            entries.push(Entry { x: i, y: i, v: 4.0 });
+            if x + 1 < nx {
+                entries.push(Entry {
+                    x: i,
+                    y: i + 1,
+                    v: -1.0,
+                });
+            }
+            if y + 1 < ny {
+                entries.push(Entry {
+                    x: i,
+                    y: i + nx,
+                    v: -1.0,
+                });
+            }
+        }
+    }
+    SparseSymmetricMatrix::new(&entries)
+}
+
+// graph laplacian of an n x n grid, singular with constant null space.
+pub fn neumann(n: usize) -> SparseSymmetricMatrix {
+    let mut entries = vec![];
+    let mut diagonals = vec![0.0; n * n];
+    for y in 0..n {
+        for x in 0..n {
+            let i = y * n + x;
+            if x + 1 < n {
+                entries.push(Entry { x: i, y: i + 1, v: -1.0 });
+                diagonals[i] += 1.0;
+                diagonals[i + 1] += 1.0;
+            }
+            if y + 1 < n {
+                entries.push(Entry { x: i, y: i + n, v: -1.0 });
+                diagonals[i] += 1.0;
+                diagonals[i + n] += 1.0;
+            }
+        }
+    }
+    for (i, &d) in diagonals.iter().enumerate() {
+        entries.push(Entry { x: i, y: i, v: d });
+    }
+    SparseSymmetricMatrix::new(&entries)
+}
+
+// upwinded convection diffusion on an n x n grid, flowing in x, with
+// its columns scaled by the given factors.
+pub fn convection_diffusion(n: usize, velocity: f64, scale: &[f64]) -> SparseRowMatrix {
+    let mut entries = vec![];
+    for y in 0..n {
+        for x in 0..n {
+            let i = y * n + x;
+            entries.push(Entry { x: i, y: i, v: 4.0 + velocity });
+            if x > 0 {
+                entries.push(Entry { x: i, y: i - 1, v: -1.0 - velocity });
+            }
+            if x + 1 < n {
+                entries.push(Entry { x: i, y: i + 1, v: -1.0 });
+            }
+            if y > 0 {
+                entries.push(Entry { x: i, y: i - n, v: -1.0 });
+            }
+            if y + 1 < n {
+                entries.push(Entry { x: i, y: i + n, v: -1.0 });
+            }
+        }
+    }
+    for entry in &mut entries {
+        entry.v *= scale[entry.y];
+    }
+    SparseRowMatrix::from_entries(&entries)
+}
+
+// the matrix plus shift times the identity.
+pub fn shift_diagonal(m: &SparseSymmetricMatrix, shift: f64) -> SparseSymmetricMatrix {
+    let mut entries = vec![];
+    for i in 0..m.length + 1 {
+        for (&j, &v) in m.indices[i].iter().zip(m.values[i].iter()) {
+            let v = if i == j { v + shift } else { v };
+            entries.push(Entry { x: i, y: j, v });
+        }
+    }
+    SparseSymmetricMatrix::new(&entries)
+}
+
+// the residual a solver returns has to be the true residual b - Ax.
+pub fn assert_true_residual<A: LinearOperator>(a: &A, rhs: &Vector, x: &Vector, r: &Vector) {
+    let mut product = Vector(vec![0.0; a.dimension()]);
+    a.apply_into(x, &mut product);
+    let residual = rhs.clone() + product.scale(-1.0);
+    for (a, b) in residual.0.iter().zip(r.0.iter()) {
+        assert!((a - b).abs() < 1e-8);
+    }
+}
+
+// the iterations preconditioned cg needs from a zero initial guess to reduce
+// the residual by 1e-8, for comparing preconditioners.
+pub fn converged_iterations<A: LinearOperator, P: ApproximateInverse>(
+    a: &A,
+    preconditioner: &P,
+    rhs: &Vector,
+) -> usize {
+    let x0 = Vector(vec![0.0; a.dimension()]);
+    let options = SolverOptions::new().relative_tolerance(1e-8);
+    let result = solve_operator(a, preconditioner, rhs, &x0, &options);
+    assert_eq!(result.stop_reason, StopReason::Converged);
+    assert_true_residual(a, rhs, &result.best_guess, &result.residual);
+    result.iterations
+}
@@ -24,6 +24,10 @@ impl Vector {
         )
     }
 
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
     // do not use the Mul trait, we want to borrow self.
     pub fn scale(&self, scale: f64) -> Vector {
         assert!(scale.is_finite());
@@ -78,6 +82,12 @@ fn test_dot() {
     assert_eq!(x.dot(&y), 8.0);
 }
 
+#[test]
+fn test_norm() {
+    let v = Vector(vec![2.0, -4.0, 4.0]);
+    assert_eq!(v.norm(), 6.0);
+}
+
 #[test]
 fn test_scale() {
     let v = Vector(vec![2.0, 4.0, 6.0]);